@@ -0,0 +1,25 @@
+-- add revocation store for access tokens
+
+CREATE TABLE
+    "revoked_tokens" (
+        jti UUID NOT NULL PRIMARY KEY,
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            revoked_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX revoked_tokens_expires_at_idx ON revoked_tokens (expires_at);
+
+-- tokens issued at or before revoked_before are rejected ("log out everywhere")
+
+CREATE TABLE
+    "user_token_revocations" (
+        user_id UUID NOT NULL PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+        revoked_before TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL
+    );
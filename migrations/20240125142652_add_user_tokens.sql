@@ -0,0 +1,20 @@
+-- add single-use tokens for email verification and password resets
+
+CREATE TABLE
+    "user_tokens" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        purpose VARCHAR(50) NOT NULL,
+        token_hash VARCHAR(64) NOT NULL UNIQUE,
+        expires_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL,
+            used_at TIMESTAMP
+        WITH
+            TIME ZONE,
+            created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX user_tokens_user_id_purpose_idx ON user_tokens (user_id, purpose);
@@ -0,0 +1,11 @@
+-- replace the generic 'user' role with real roles
+
+UPDATE users SET role = 'author' WHERE role = 'user';
+
+ALTER TABLE users ALTER COLUMN role SET DEFAULT 'author';
+
+ALTER TABLE users
+ADD
+    CONSTRAINT users_role_check CHECK (
+        role IN ('admin', 'editor', 'author', 'reader')
+    );
@@ -0,0 +1,21 @@
+-- add account suspension and an audit trail for admin actions
+
+ALTER TABLE users ADD COLUMN suspended_at TIMESTAMP WITH TIME ZONE;
+
+ALTER TABLE users ADD COLUMN suspension_reason TEXT;
+
+CREATE TABLE
+    "audit_logs" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        actor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+        action VARCHAR(100) NOT NULL,
+        target_user_id UUID,
+        details JSONB NOT NULL DEFAULT '{}',
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX audit_logs_created_at_idx ON audit_logs (created_at DESC);
+
+CREATE INDEX audit_logs_target_user_id_idx ON audit_logs (target_user_id);
@@ -0,0 +1,3 @@
+-- add deleted_at column to posts table for soft deletes
+
+ALTER TABLE posts ADD COLUMN deleted_at TIMESTAMP WITH TIME ZONE;
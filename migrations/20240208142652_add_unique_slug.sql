@@ -0,0 +1,18 @@
+-- make post slugs unique, suffixing existing duplicates with a counter
+
+WITH ranked AS (
+        SELECT
+            id,
+            slug,
+            ROW_NUMBER() OVER (
+                PARTITION BY slug
+                ORDER BY created_at, id
+            ) AS n
+        FROM posts
+    )
+UPDATE posts
+SET slug = ranked.slug || '-' || ranked.n
+FROM ranked
+WHERE posts.id = ranked.id AND ranked.n > 1;
+
+CREATE UNIQUE INDEX posts_slug_key ON posts (slug);
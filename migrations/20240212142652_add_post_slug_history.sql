@@ -0,0 +1,12 @@
+-- keep previous slugs of posts so old links can be redirected
+
+CREATE TABLE
+    "post_slug_history" (
+        slug VARCHAR(255) NOT NULL PRIMARY KEY,
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX post_slug_history_post_id_idx ON post_slug_history (post_id);
@@ -0,0 +1,31 @@
+-- add publication status to posts, existing posts stay published
+
+ALTER TABLE posts
+ADD
+    COLUMN status VARCHAR(20) NOT NULL DEFAULT 'published',
+ADD
+    COLUMN published_at TIMESTAMP
+WITH
+    TIME ZONE;
+
+UPDATE posts SET published_at = created_at;
+
+ALTER TABLE posts ALTER COLUMN status SET DEFAULT 'draft';
+
+ALTER TABLE posts
+ADD
+    CONSTRAINT posts_status_check CHECK (
+        status IN (
+            'draft',
+            'scheduled',
+            'published',
+            'archived'
+        )
+    ),
+ADD
+    CONSTRAINT posts_published_at_check CHECK (
+        status NOT IN ('scheduled', 'published')
+        OR published_at IS NOT NULL
+    );
+
+CREATE INDEX posts_status_published_at_idx ON posts (status, published_at);
@@ -0,0 +1,37 @@
+-- keep every revision of a post's title, content and photo
+
+CREATE TABLE
+    "post_revisions" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        revision INTEGER NOT NULL,
+        title VARCHAR(255) NOT NULL,
+        content TEXT NOT NULL,
+        photo VARCHAR(255) NOT NULL,
+        editor_id UUID REFERENCES users(id) ON DELETE SET NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW(),
+            UNIQUE (post_id, revision)
+    );
+
+-- the current state of existing posts becomes their first revision
+INSERT INTO
+    post_revisions (
+        post_id,
+        revision,
+        title,
+        content,
+        photo,
+        editor_id,
+        created_at
+    )
+SELECT
+    id,
+    1,
+    title,
+    content,
+    photo,
+    user_id,
+    COALESCE(updated_at, created_at, NOW())
+FROM posts;
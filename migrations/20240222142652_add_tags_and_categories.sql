@@ -0,0 +1,42 @@
+-- add tags and hierarchical categories for posts
+
+CREATE TABLE
+    "tags" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        name VARCHAR(50) NOT NULL,
+        slug VARCHAR(100) NOT NULL UNIQUE,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE TABLE
+    "post_tags" (
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        tag_id UUID NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+        PRIMARY KEY (post_id, tag_id)
+    );
+
+CREATE INDEX post_tags_tag_id_idx ON post_tags (tag_id);
+
+CREATE TABLE
+    "categories" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        name VARCHAR(100) NOT NULL,
+        slug VARCHAR(100) NOT NULL UNIQUE,
+        parent_id UUID REFERENCES categories(id) ON DELETE RESTRICT,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW()
+    );
+
+CREATE INDEX categories_parent_id_idx ON categories (parent_id);
+
+CREATE TABLE
+    "post_categories" (
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+        PRIMARY KEY (post_id, category_id)
+    );
+
+CREATE INDEX post_categories_category_id_idx ON post_categories (category_id);
@@ -0,0 +1,19 @@
+-- full-text search over post titles and content, in the language of each post
+
+ALTER TABLE posts
+ADD
+    COLUMN search_language REGCONFIG NOT NULL DEFAULT 'english';
+
+ALTER TABLE posts
+ADD
+    COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+        setweight(
+            to_tsvector(search_language, title),
+            'A'
+        ) || setweight(
+            to_tsvector(search_language, content),
+            'B'
+        )
+    ) STORED;
+
+CREATE INDEX posts_search_vector_idx ON posts USING GIN (search_vector);
@@ -0,0 +1,12 @@
+-- keyset pagination needs non-null sort keys and matching indexes
+
+UPDATE posts SET created_at = NOW() WHERE created_at IS NULL;
+
+ALTER TABLE posts ALTER COLUMN created_at SET NOT NULL;
+
+CREATE INDEX posts_published_at_id_idx ON posts (published_at DESC, id DESC)
+WHERE
+    status = 'published'
+    AND deleted_at IS NULL;
+
+CREATE INDEX posts_user_id_created_at_id_idx ON posts (user_id, created_at DESC, id DESC);
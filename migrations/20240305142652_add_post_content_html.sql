@@ -0,0 +1,13 @@
+-- add content format and rendered html to posts, existing posts are rendered at startup
+
+ALTER TABLE posts
+ADD
+    COLUMN content_format VARCHAR(20) NOT NULL DEFAULT 'markdown',
+ADD
+    COLUMN content_html TEXT;
+
+ALTER TABLE posts
+ADD
+    CONSTRAINT posts_content_format_check CHECK (
+        content_format IN ('markdown', 'html')
+    );
@@ -0,0 +1,28 @@
+-- add threaded comments on posts, post authors can lock them
+
+ALTER TABLE posts
+ADD
+    COLUMN comments_locked BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE
+    "comments" (
+        id UUID NOT NULL PRIMARY KEY DEFAULT (uuid_generate_v4()),
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        parent_id UUID REFERENCES comments(id) ON DELETE CASCADE,
+        content TEXT NOT NULL,
+        content_html TEXT NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW(),
+            updated_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW(),
+            deleted_at TIMESTAMP
+        WITH
+            TIME ZONE
+    );
+
+CREATE INDEX comments_post_id_created_at_idx ON comments (post_id, created_at DESC, id DESC);
+
+CREATE INDEX comments_parent_id_idx ON comments (parent_id);
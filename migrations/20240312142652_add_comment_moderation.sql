@@ -0,0 +1,30 @@
+-- add moderation status to comments, existing comments stay approved
+
+ALTER TABLE comments
+ADD
+    COLUMN status VARCHAR(20) NOT NULL DEFAULT 'approved',
+ADD
+    COLUMN spam_score REAL NOT NULL DEFAULT 0,
+ADD
+    COLUMN moderated_by UUID REFERENCES users(id) ON DELETE SET NULL,
+ADD
+    COLUMN moderated_at TIMESTAMP
+WITH
+    TIME ZONE;
+
+ALTER TABLE comments ALTER COLUMN status SET DEFAULT 'pending';
+
+ALTER TABLE comments
+ADD
+    CONSTRAINT comments_status_check CHECK (
+        status IN (
+            'pending',
+            'approved',
+            'rejected',
+            'spam'
+        )
+    );
+
+CREATE INDEX comments_status_created_at_idx ON comments (status, created_at DESC, id DESC);
+
+CREATE INDEX comments_user_id_created_at_idx ON comments (user_id, created_at);
@@ -0,0 +1,48 @@
+-- add emoji reactions on posts, one of each kind per user, with per post counters
+
+CREATE TABLE
+    "post_reactions" (
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        reaction VARCHAR(20) NOT NULL,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW(),
+            CONSTRAINT post_reactions_unique UNIQUE (post_id, user_id, reaction)
+    );
+
+CREATE INDEX post_reactions_user_id_idx ON post_reactions (user_id);
+
+CREATE TABLE
+    "post_reaction_counts" (
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        reaction VARCHAR(20) NOT NULL,
+        count INTEGER NOT NULL,
+        PRIMARY KEY (post_id, reaction)
+    );
+
+-- the counters are kept by triggers so reactions removed by cascading deletes
+-- are uncounted too
+
+CREATE FUNCTION post_reaction_counts_update() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO post_reaction_counts (post_id, reaction, count)
+        VALUES (NEW.post_id, NEW.reaction, 1)
+        ON CONFLICT (post_id, reaction)
+        DO UPDATE SET count = post_reaction_counts.count + 1;
+    ELSE
+        UPDATE post_reaction_counts
+        SET count = count - 1
+        WHERE post_id = OLD.post_id AND reaction = OLD.reaction;
+
+        DELETE FROM post_reaction_counts
+        WHERE post_id = OLD.post_id AND reaction = OLD.reaction AND count <= 0;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER post_reactions_count
+AFTER INSERT OR DELETE ON post_reactions
+FOR EACH ROW EXECUTE FUNCTION post_reaction_counts_update();
@@ -0,0 +1,15 @@
+-- let readers bookmark posts to read later
+
+CREATE TABLE
+    "bookmarks" (
+        user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+        post_id UUID NOT NULL REFERENCES posts(id) ON DELETE CASCADE,
+        created_at TIMESTAMP
+        WITH
+            TIME ZONE NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (user_id, post_id)
+    );
+
+CREATE INDEX bookmarks_user_id_created_at_idx ON bookmarks (user_id, created_at DESC, post_id DESC);
+
+CREATE INDEX bookmarks_post_id_idx ON bookmarks (post_id);
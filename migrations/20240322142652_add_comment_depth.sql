@@ -0,0 +1,12 @@
+-- record how deeply each comment is nested so reply chains can be capped
+
+ALTER TABLE comments ADD COLUMN depth INTEGER NOT NULL DEFAULT 0;
+
+WITH RECURSIVE thread AS (
+    SELECT id, 0 AS depth FROM comments WHERE parent_id IS NULL
+    UNION ALL
+    SELECT comments.id, thread.depth + 1
+    FROM comments
+    JOIN thread ON comments.parent_id = thread.id
+)
+UPDATE comments SET depth = thread.depth FROM thread WHERE comments.id = thread.id;
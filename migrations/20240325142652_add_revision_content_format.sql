@@ -0,0 +1,13 @@
+-- remember how each revision's content is written so restores render it right
+
+ALTER TABLE post_revisions
+ADD
+    COLUMN content_format VARCHAR(20) NOT NULL DEFAULT 'markdown' CHECK (
+        content_format IN ('markdown', 'html')
+    );
+
+-- the format wasn't recorded before, the post's current one is the best guess
+UPDATE post_revisions
+SET content_format = posts.content_format
+FROM posts
+WHERE posts.id = post_revisions.post_id;
@@ -0,0 +1,48 @@
+-- keep a total of every post's reactions for the popularity sort, next to the
+-- per kind counters. It's a table of its own since updating posts rows would
+-- recompute their search vector on every reaction.
+
+CREATE TABLE
+    "post_reaction_totals" (
+        post_id UUID NOT NULL PRIMARY KEY REFERENCES posts(id) ON DELETE CASCADE,
+        total INTEGER NOT NULL
+    );
+
+CREATE INDEX post_reaction_totals_total_idx ON post_reaction_totals (total DESC, post_id);
+
+INSERT INTO post_reaction_totals (post_id, total)
+SELECT post_id, SUM(count)
+FROM post_reaction_counts
+GROUP BY post_id
+HAVING SUM(count) > 0;
+
+CREATE OR REPLACE FUNCTION post_reaction_counts_update() RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO post_reaction_counts (post_id, reaction, count)
+        VALUES (NEW.post_id, NEW.reaction, 1)
+        ON CONFLICT (post_id, reaction)
+        DO UPDATE SET count = post_reaction_counts.count + 1;
+
+        INSERT INTO post_reaction_totals (post_id, total)
+        VALUES (NEW.post_id, 1)
+        ON CONFLICT (post_id)
+        DO UPDATE SET total = post_reaction_totals.total + 1;
+    ELSE
+        UPDATE post_reaction_counts
+        SET count = count - 1
+        WHERE post_id = OLD.post_id AND reaction = OLD.reaction;
+
+        DELETE FROM post_reaction_counts
+        WHERE post_id = OLD.post_id AND reaction = OLD.reaction AND count <= 0;
+
+        UPDATE post_reaction_totals
+        SET total = total - 1
+        WHERE post_id = OLD.post_id;
+
+        DELETE FROM post_reaction_totals
+        WHERE post_id = OLD.post_id AND total <= 0;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,5 @@
+-- the posts listing can include drafts, which are ordered by creation until they have a publication date
+
+CREATE INDEX posts_listed_at_id_idx ON posts (COALESCE(published_at, created_at) DESC, id DESC)
+WHERE
+    deleted_at IS NULL;
@@ -0,0 +1,9 @@
+-- the posts listing is paged on (created_at, id) like the per-user listing
+
+DROP INDEX IF EXISTS posts_listed_at_id_idx;
+
+DROP INDEX IF EXISTS posts_published_at_id_idx;
+
+CREATE INDEX posts_created_at_id_idx ON posts (created_at DESC, id DESC)
+WHERE
+    deleted_at IS NULL;
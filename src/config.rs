@@ -1,22 +1,282 @@
+use std::fmt;
+
+use chrono::Duration;
+
 #[derive(Debug, Clone)]
 pub struct Config {
     pub database_url: String,
     pub jwt_secret: String,
-    pub jwt_expires_in: String,
-    pub jwt_maxage: i32,
+    pub jwt_algorithm: String,
+    pub jwt_key_id: Option<String>,
+    pub jwt_private_key_path: Option<String>,
+    pub jwt_public_keys: Vec<(String, String)>,
+    pub jwt_issuer: Option<String>,
+    pub jwt_audience: Option<String>,
+    pub jwt_expires_in: Duration,
+    pub jwt_maxage: Duration,
+    pub refresh_token_maxage: Duration,
+    pub app_url: String,
+    pub verification_token_maxage: Duration,
+    pub password_reset_token_maxage: Duration,
+    pub mailer: String,
+    pub mail_log_path: Option<String>,
+    pub smtp_host: String,
+    pub smtp_port: u16,
+    pub smtp_username: Option<String>,
+    pub smtp_password: Option<String>,
+    pub smtp_from: String,
+    pub post_scheduler_interval: Duration,
+    pub search_languages: Vec<String>,
+    pub spam_threshold: f32,
+    pub spam_max_links: usize,
+    pub spam_blocklist: Vec<String>,
+    pub spam_max_comments: usize,
+    pub spam_rate_window: Duration,
 }
 
+#[derive(Debug)]
+pub struct ConfigError(pub String);
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Config error: {}", self.0)
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
 impl Config {
-    pub fn init() -> Config {
-        let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-        let jwt_secret = std::env::var("JWT_SECRET").expect("JWT_SECRET must be set");
-        let jwt_expires_in = std::env::var("JWT_EXPIRED_IN").expect("JWT_EXPIRED_IN must be set");
-        let jwt_maxage = std::env::var("JWT_MAXAGE").expect("JWT_MAXAGE must be set");
-        Config {
+    pub fn init() -> Result<Config, ConfigError> {
+        let database_url = required("DATABASE_URL")?;
+        let jwt_algorithm = std::env::var("JWT_ALGORITHM").unwrap_or_else(|_| "HS256".to_string());
+        // the asymmetric algorithms sign with JWT_PRIVATE_KEY_PATH instead
+        let jwt_secret = match jwt_algorithm.as_str() {
+            "HS256" => required("JWT_SECRET")?,
+            _ => std::env::var("JWT_SECRET").unwrap_or_default(),
+        };
+        let jwt_key_id = std::env::var("JWT_KEY_ID").ok();
+        let jwt_private_key_path = std::env::var("JWT_PRIVATE_KEY_PATH").ok();
+        // comma separated list of kid=path pairs
+        let jwt_public_keys = std::env::var("JWT_PUBLIC_KEYS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (kid, path) = entry.split_once('=').ok_or_else(|| {
+                    ConfigError(format!(
+                        "JWT_PUBLIC_KEYS entries must look like kid=path, got {:?}",
+                        entry
+                    ))
+                })?;
+                Ok((kid.trim().to_string(), path.trim().to_string()))
+            })
+            .collect::<Result<Vec<_>, ConfigError>>()?;
+        let jwt_issuer = std::env::var("JWT_ISSUER").ok();
+        let jwt_audience = std::env::var("JWT_AUDIENCE").ok();
+        let jwt_expires_in = duration("JWT_EXPIRED_IN", &required("JWT_EXPIRED_IN")?)?;
+        let jwt_maxage = duration("JWT_MAXAGE", &required("JWT_MAXAGE")?)?;
+        // refresh tokens are long-lived
+        let refresh_token_maxage = duration(
+            "REFRESH_TOKEN_MAXAGE",
+            &std::env::var("REFRESH_TOKEN_MAXAGE").unwrap_or_else(|_| "30d".to_string()),
+        )?;
+        // base url of the front-end, used to build links in emails
+        let app_url = std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
+        let verification_token_maxage = duration(
+            "VERIFICATION_TOKEN_MAXAGE",
+            &std::env::var("VERIFICATION_TOKEN_MAXAGE").unwrap_or_else(|_| "24h".to_string()),
+        )?;
+        let password_reset_token_maxage = duration(
+            "PASSWORD_RESET_TOKEN_MAXAGE",
+            &std::env::var("PASSWORD_RESET_TOKEN_MAXAGE").unwrap_or_else(|_| "1h".to_string()),
+        )?;
+        let mailer = std::env::var("MAILER").unwrap_or_else(|_| "log".to_string());
+        let mail_log_path = std::env::var("MAIL_LOG_PATH").ok();
+        let smtp_host = std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+        let smtp_port = std::env::var("SMTP_PORT").unwrap_or_else(|_| "587".to_string());
+        let smtp_username = std::env::var("SMTP_USERNAME").ok();
+        let smtp_password = std::env::var("SMTP_PASSWORD").ok();
+        let smtp_from =
+            std::env::var("SMTP_FROM").unwrap_or_else(|_| "Blog <no-reply@localhost>".to_string());
+        // how often scheduled posts are checked for publication
+        let post_scheduler_interval = duration(
+            "POST_SCHEDULER_INTERVAL",
+            &std::env::var("POST_SCHEDULER_INTERVAL").unwrap_or_else(|_| "30s".to_string()),
+        )?;
+        // text search configurations posts can be indexed with, the first is the default
+        let search_languages: Vec<String> = std::env::var("SEARCH_LANGUAGES")
+            .unwrap_or_else(|_| "english".to_string())
+            .split(',')
+            .map(|language| language.trim().to_lowercase())
+            .filter(|language| !language.is_empty())
+            .collect();
+        if search_languages.is_empty() {
+            return Err(ConfigError(
+                "SEARCH_LANGUAGES must name at least one text search configuration".to_string(),
+            ));
+        }
+        // comments scoring at least the threshold are held as spam
+        let spam_threshold = number("SPAM_THRESHOLD", "1.0")?;
+        let spam_max_links = number("SPAM_MAX_LINKS", "2")?;
+        // comma separated phrases
+        let spam_blocklist: Vec<String> = std::env::var("SPAM_BLOCKLIST")
+            .unwrap_or_default()
+            .split(',')
+            .map(|phrase| phrase.trim().to_string())
+            .filter(|phrase| !phrase.is_empty())
+            .collect();
+        // more than SPAM_MAX_COMMENTS comments within SPAM_RATE_WINDOW looks like a bot
+        let spam_max_comments = number("SPAM_MAX_COMMENTS", "5")?;
+        let spam_rate_window = duration(
+            "SPAM_RATE_WINDOW",
+            &std::env::var("SPAM_RATE_WINDOW").unwrap_or_else(|_| "10m".to_string()),
+        )?;
+        Ok(Config {
             database_url,
             jwt_secret,
+            jwt_algorithm,
+            jwt_key_id,
+            jwt_private_key_path,
+            jwt_public_keys,
+            jwt_issuer,
+            jwt_audience,
             jwt_expires_in,
-            jwt_maxage: jwt_maxage.parse::<i32>().unwrap(),
+            jwt_maxage,
+            refresh_token_maxage,
+            app_url,
+            verification_token_maxage,
+            password_reset_token_maxage,
+            mailer,
+            mail_log_path,
+            smtp_host,
+            smtp_port: smtp_port.parse::<u16>().map_err(|_| {
+                ConfigError(format!(
+                    "SMTP_PORT must be a port number, got {:?}",
+                    smtp_port
+                ))
+            })?,
+            smtp_username,
+            smtp_password,
+            smtp_from,
+            post_scheduler_interval,
+            search_languages,
+            spam_threshold,
+            spam_max_links,
+            spam_blocklist,
+            spam_max_comments,
+            spam_rate_window,
+        })
+    }
+}
+
+fn required(name: &str) -> Result<String, ConfigError> {
+    std::env::var(name).map_err(|_| ConfigError(format!("{} must be set", name)))
+}
+
+fn number<T: std::str::FromStr>(name: &str, default: &str) -> Result<T, ConfigError> {
+    let value = std::env::var(name).unwrap_or_else(|_| default.to_string());
+    value
+        .trim()
+        .parse()
+        .map_err(|_| ConfigError(format!("{} must be a number, got {:?}", name, value)))
+}
+
+fn duration(name: &str, value: &str) -> Result<Duration, ConfigError> {
+    parse_duration(value).ok_or_else(|| {
+        ConfigError(format!(
+            "{} must be a duration like \"15m\", \"12h\" or \"7d\", got {:?}",
+            name, value
+        ))
+    })
+}
+
+/// Parses durations such as "90s", "15m", "1h30m" or "7d". A bare number is
+/// read as minutes, which is what JWT_MAXAGE has always meant.
+fn parse_duration(value: &str) -> Option<Duration> {
+    // anything longer than ~100 years is certainly a typo
+    const MAX_SECONDS: i64 = 100 * 365 * 24 * 60 * 60;
+
+    let value = value.trim();
+    let value = match value.parse::<i64>() {
+        Ok(_) => format!("{}m", value),
+        Err(_) => value.to_string(),
+    };
+
+    let mut seconds: i64 = 0;
+    let mut digits = String::new();
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
         }
+
+        let amount = digits.parse::<i64>().ok()?;
+        digits.clear();
+        let unit = match c {
+            's' => 1,
+            'm' => 60,
+            'h' => 60 * 60,
+            'd' => 24 * 60 * 60,
+            'w' => 7 * 24 * 60 * 60,
+            _ => return None,
+        };
+        seconds = seconds.checked_add(amount.checked_mul(unit)?)?;
+    }
+
+    // trailing digits without a unit
+    if !digits.is_empty() || seconds <= 0 || seconds > MAX_SECONDS {
+        return None;
+    }
+
+    Some(Duration::seconds(seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_units_and_combinations() {
+        assert_eq!(parse_duration("90s"), Some(Duration::seconds(90)));
+        assert_eq!(parse_duration("15m"), Some(Duration::minutes(15)));
+        assert_eq!(parse_duration("1h30m"), Some(Duration::minutes(90)));
+        assert_eq!(parse_duration("7d"), Some(Duration::days(7)));
+        assert_eq!(parse_duration("2w"), Some(Duration::days(14)));
+        assert_eq!(parse_duration(" 1d12h "), Some(Duration::hours(36)));
+    }
+
+    #[test]
+    fn bare_numbers_are_minutes() {
+        assert_eq!(parse_duration("10"), Some(Duration::minutes(10)));
+        assert_eq!(parse_duration("60"), Some(Duration::hours(1)));
+    }
+
+    #[test]
+    fn rejects_empty_and_zero_durations() {
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("   "), None);
+        assert_eq!(parse_duration("0m"), None);
+        assert_eq!(parse_duration("0"), None);
+        assert_eq!(parse_duration("-5"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_units_and_missing_amounts() {
+        assert_eq!(parse_duration("5y"), None);
+        assert_eq!(parse_duration("10 m"), None);
+        assert_eq!(parse_duration("1.5h"), None);
+        assert_eq!(parse_duration("h"), None);
+        assert_eq!(parse_duration("1h30"), None);
+        assert_eq!(parse_duration("15M"), None);
+    }
+
+    #[test]
+    fn rejects_overflow() {
+        assert_eq!(parse_duration("99999999999999999999s"), None);
+        assert_eq!(parse_duration(&format!("{}w", i64::MAX)), None);
+        assert_eq!(parse_duration(&format!("{}s1s", i64::MAX)), None);
+        assert_eq!(parse_duration("36501d"), None);
+        assert_eq!(parse_duration("36500d"), Some(Duration::days(36500)));
     }
 }
@@ -0,0 +1,378 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    handler::user::{
+        database_error, filter_user_record, hash_password, revoke_sessions,
+        send_password_reset_email,
+    },
+    model::audit_log::{AuditLog, AuditLogQuery},
+    model::user::{AdminUsersQuery, SuspendUserSchema, UpdateRoleSchema, User},
+    rbac::{require, Authorized, Role},
+    response::FilteredUser,
+    token::generate_token,
+    AppState,
+};
+
+const MAX_PER_PAGE: usize = 100;
+
+pub async fn list_users_handler(
+    Authorized(_admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<AdminUsersQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, MAX_PER_PAGE);
+    let offset = ((page - 1) * per_page) as i64;
+
+    // escape LIKE wildcards so the search is a plain substring match
+    let search = query.q.as_ref().map(|q| {
+        format!(
+            "%{}%",
+            q.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        )
+    });
+
+    let users = sqlx::query_as!(
+        User,
+        r#"
+        SELECT *
+        FROM users
+        WHERE ($1::VARCHAR IS NULL OR role = $1)
+            AND ($2::BOOLEAN IS NULL OR verified = $2)
+            AND ($3::BOOLEAN IS NULL OR (suspended_at IS NOT NULL) = $3)
+            AND ($4::VARCHAR IS NULL OR name ILIKE $4 OR email ILIKE $4)
+        ORDER BY created_at DESC
+        LIMIT $5 OFFSET $6
+        "#,
+        query.role,
+        query.verified,
+        query.suspended,
+        search,
+        per_page as i64,
+        offset,
+    )
+    .fetch_all(&data.db)
+    .await
+    .map_err(database_error)?;
+
+    let total = sqlx::query_scalar!(
+        r#"
+        SELECT COUNT(*) AS "count!"
+        FROM users
+        WHERE ($1::VARCHAR IS NULL OR role = $1)
+            AND ($2::BOOLEAN IS NULL OR verified = $2)
+            AND ($3::BOOLEAN IS NULL OR (suspended_at IS NOT NULL) = $3)
+            AND ($4::VARCHAR IS NULL OR name ILIKE $4 OR email ILIKE $4)
+        "#,
+        query.role,
+        query.verified,
+        query.suspended,
+        search,
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(database_error)?;
+
+    let users: Vec<FilteredUser> = users.iter().map(filter_user_record).collect();
+
+    Ok(Json(json!({
+        "status": "success",
+        "page": page,
+        "per_page": per_page,
+        "total": total,
+        "results": users.len(),
+        "users": users,
+    })))
+}
+
+pub async fn get_user_handler(
+    Authorized(_admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let user = fetch_user(id, &data).await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": json!({ "user": filter_user_record(&user) }),
+    })))
+}
+
+pub async fn update_user_role_handler(
+    Authorized(admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateRoleSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let role = Role::parse(&body.role).ok_or_else(|| {
+        let error_response = json!({
+            "status": "fail",
+            "message": format!("Invalid role: {}", body.role),
+        });
+        (StatusCode::BAD_REQUEST, Json(error_response))
+    })?;
+
+    reject_self(&admin, id, "change the role of")?;
+    let user = fetch_user(id, &data).await?;
+
+    let mut tx = data.db.begin().await.map_err(database_error)?;
+
+    let updated = sqlx::query_as!(
+        User,
+        "UPDATE users SET role = $1, updated_at = NOW() WHERE id = $2 RETURNING *",
+        role.as_str(),
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    AuditLog::record(
+        admin.id,
+        "user.role_changed",
+        Some(id),
+        json!({ "from": user.role, "to": role.as_str() }),
+        &mut *tx,
+    )
+    .await
+    .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": json!({ "user": filter_user_record(&updated) }),
+    })))
+}
+
+pub async fn suspend_user_handler(
+    Authorized(admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<SuspendUserSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if body.reason.trim().is_empty() {
+        let error_response = json!({
+            "status": "fail",
+            "message": "A reason for the suspension is required",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    reject_self(&admin, id, "suspend")?;
+    fetch_user(id, &data).await?;
+
+    let mut tx = data.db.begin().await.map_err(database_error)?;
+
+    let updated = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET suspended_at = NOW(), suspension_reason = $1, updated_at = NOW()
+        WHERE id = $2
+        RETURNING *
+        "#,
+        body.reason,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    AuditLog::record(
+        admin.id,
+        "user.suspended",
+        Some(id),
+        json!({ "reason": body.reason }),
+        &mut *tx,
+    )
+    .await
+    .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    revoke_sessions(id, &data).await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": json!({ "user": filter_user_record(&updated) }),
+    })))
+}
+
+pub async fn unsuspend_user_handler(
+    Authorized(admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    fetch_user(id, &data).await?;
+
+    let mut tx = data.db.begin().await.map_err(database_error)?;
+
+    let updated = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET suspended_at = NULL, suspension_reason = NULL, updated_at = NOW()
+        WHERE id = $1
+        RETURNING *
+        "#,
+        id
+    )
+    .fetch_one(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    AuditLog::record(admin.id, "user.unsuspended", Some(id), json!({}), &mut *tx)
+        .await
+        .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "data": json!({ "user": filter_user_record(&updated) }),
+    })))
+}
+
+pub async fn force_password_reset_handler(
+    Authorized(admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let user = fetch_user(id, &data).await?;
+
+    // replace the password with one nobody knows, the user has to go through
+    // the emailed reset link to get back in
+    let hashed_password = hash_password(&generate_token())?;
+
+    let mut tx = data.db.begin().await.map_err(database_error)?;
+
+    sqlx::query!(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+        hashed_password,
+        id
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(database_error)?;
+
+    AuditLog::record(
+        admin.id,
+        "user.password_reset_forced",
+        Some(id),
+        json!({}),
+        &mut *tx,
+    )
+    .await
+    .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    revoke_sessions(id, &data).await?;
+
+    send_password_reset_email(&user, &data).await.map_err(|e| {
+        let error_response = json!({
+            "status": "error",
+            "message": format!("Error sending password reset email: {}", e),
+        });
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
+    })?;
+
+    Ok(Json(json!({ "status": "success" })))
+}
+
+pub async fn delete_user_handler(
+    Authorized(admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    reject_self(&admin, id, "delete")?;
+    let user = fetch_user(id, &data).await?;
+
+    let mut tx = data.db.begin().await.map_err(database_error)?;
+
+    sqlx::query!("DELETE FROM users WHERE id = $1", id)
+        .execute(&mut *tx)
+        .await
+        .map_err(database_error)?;
+
+    AuditLog::record(
+        admin.id,
+        "user.deleted",
+        Some(id),
+        json!({ "email": user.email, "name": user.name }),
+        &mut *tx,
+    )
+    .await
+    .map_err(database_error)?;
+
+    tx.commit().await.map_err(database_error)?;
+
+    Ok(Json(json!({ "status": "success" })))
+}
+
+pub async fn list_audit_logs_handler(
+    Authorized(_admin, _): Authorized<require::ManageUsers>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(20).clamp(1, MAX_PER_PAGE);
+
+    let entries = AuditLog::find_all(query.user_id, page, per_page, &data.db)
+        .await
+        .map_err(database_error)?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "page": page,
+        "per_page": per_page,
+        "results": entries.len(),
+        "entries": entries,
+    })))
+}
+
+async fn fetch_user(
+    id: Uuid,
+    data: &AppState,
+) -> Result<User, (StatusCode, Json<serde_json::Value>)> {
+    sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", id)
+        .fetch_optional(&data.db)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(|| {
+            let error_response = json!({
+                "status": "fail",
+                "message": "User not found",
+            });
+            (StatusCode::NOT_FOUND, Json(error_response))
+        })
+}
+
+/// Keeps admins from locking themselves out.
+fn reject_self(
+    admin: &User,
+    id: Uuid,
+    action: &str,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if admin.id == id {
+        let error_response = json!({
+            "status": "fail",
+            "message": format!("You can't {} your own account", action),
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    Ok(())
+}
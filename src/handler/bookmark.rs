@@ -0,0 +1,127 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    handler::post::{find_published_post, parse_cursor, post_responses},
+    model::bookmark::{Bookmark, BookmarksQuery},
+    model::user::User,
+    pagination::{self, Cursor, Page},
+    response::BookmarkedPost,
+    AppState,
+};
+
+/// Lists the posts the user bookmarked, most recently bookmarked first.
+pub async fn list_bookmarks_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<BookmarksQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let per_page = pagination::per_page(query.per_page);
+
+    let error = |e| {
+        eprintln!("Error getting bookmarks: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting bookmarks" })),
+        )
+    };
+
+    let bookmarks = Bookmark::find_posts(user.id, Cursor::key(cursor), per_page + 1, &data.db)
+        .await
+        .map_err(error)?;
+    let continues = pagination::continues(cursor, |after| {
+        Bookmark::find_posts(user.id, after, 1, &data.db)
+    })
+    .await
+    .map_err(error)?;
+
+    let params = [("per_page", query.per_page.map(|_| per_page.to_string()))];
+    let mut page = Page::keyset(
+        bookmarks,
+        per_page,
+        cursor,
+        continues,
+        |(bookmark, _)| (bookmark.created_at, bookmark.post_id),
+        |cursor| pagination::link("/api/users/me/bookmarks", &params, cursor),
+    );
+
+    let (bookmarks, posts): (Vec<Bookmark>, Vec<_>) =
+        std::mem::take(&mut page.data).into_iter().unzip();
+    let bookmarked: Vec<BookmarkedPost> = post_responses(posts, Some(&user), &data.db)
+        .await?
+        .into_iter()
+        .zip(bookmarks)
+        .map(|(post, bookmark)| BookmarkedPost {
+            post,
+            bookmarked_at: bookmark.created_at,
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(page.with_data(bookmarked))))
+}
+
+/// Bookmarks a published post, bookmarking it again changes nothing.
+pub async fn add_bookmark_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let post = find_published_post(id, &data.db).await?;
+
+    match Bookmark::insert(user.id, post.id, &data.db).await {
+        Ok(bookmark) => Ok((
+            StatusCode::OK,
+            Json(json!({
+                "status": "success",
+                "data": {
+                    "post_id": bookmark.post_id,
+                    "bookmarked_at": bookmark.created_at,
+                }
+            })),
+        )),
+        Err(e) => {
+            eprintln!("Error adding bookmark: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error adding bookmark" })),
+            ))
+        }
+    }
+}
+
+/// Removes a bookmark, also when its post is no longer published.
+pub async fn remove_bookmark_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match Bookmark::delete(user.id, id, &data.db).await {
+        Ok(true) => Ok((
+            StatusCode::OK,
+            Json(json!({
+                "status": "success",
+                "data": { "post_id": id }
+            })),
+        )),
+        Ok(false) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Bookmark not found" })),
+        )),
+        Err(e) => {
+            eprintln!("Error removing bookmark: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error removing bookmark" })),
+            ))
+        }
+    }
+}
@@ -0,0 +1,629 @@
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use chrono::prelude::*;
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::{
+    handler::post::{find_editable_post, find_published_post, parse_cursor, post_responses},
+    model::{
+        comment::{
+            Comment, CommentStatus, CommentsQuery, CreateCommentSchema, LockCommentsSchema,
+            ModerateCommentSchema, ModerationQuery, UpdateCommentSchema,
+        },
+        post::Post,
+        user::User,
+    },
+    pagination::{self, Cursor, Page},
+    rbac::{require, Authorized, Permission},
+    response::{CommentResponse, QueuedComment},
+    spam::CommentSample,
+    AppState,
+};
+
+const MAX_COMMENT_LENGTH: usize = 10_000;
+/// How deeply replies may nest, top level comments are at depth 0.
+const MAX_REPLY_DEPTH: i32 = 10;
+
+pub async fn list_comments_handler(
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CommentsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let tree = match query.view.as_deref() {
+        None | Some("tree") => true,
+        Some("flat") => false,
+        Some(_) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "message": "View must be tree or flat" })),
+            ));
+        }
+    };
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let per_page = pagination::per_page(query.per_page);
+    let error = |e| {
+        eprintln!("Error getting comments: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting comments" })),
+        )
+    };
+
+    find_published_post(id, &data.db).await?;
+
+    let comments = Comment::find_by_post(id, tree, Cursor::key(cursor), per_page + 1, &data.db)
+        .await
+        .map_err(error)?;
+    let continues = pagination::continues(cursor, |after| {
+        Comment::find_by_post(id, tree, after, 1, &data.db)
+    })
+    .await
+    .map_err(error)?;
+
+    let params = [
+        ("view", query.view.clone()),
+        ("per_page", query.per_page.map(|_| per_page.to_string())),
+    ];
+    let path = format!("/api/post/{}/comments", id);
+    let mut page = Page::keyset(
+        comments,
+        per_page,
+        cursor,
+        continues,
+        |comment| (comment.created_at, comment.id),
+        |cursor| pagination::link(&path, &params, cursor),
+    );
+
+    let comments = std::mem::take(&mut page.data);
+    let comments: Vec<CommentResponse> = if tree {
+        let root_ids: Vec<Uuid> = comments.iter().map(|comment| comment.id).collect();
+        let replies = Comment::find_replies(&root_ids, MAX_REPLY_DEPTH, &data.db)
+            .await
+            .map_err(error)?;
+        thread(comments, replies)
+    } else {
+        comments.into_iter().map(CommentResponse::from).collect()
+    };
+
+    Ok((StatusCode::OK, Json(page.with_data(comments))))
+}
+
+pub async fn create_comment_handler(
+    Authorized(user, _): Authorized<require::CreateComment>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<CreateCommentSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let content = valid_content(&body.content)?;
+
+    let post = find_published_post(id, &data.db).await?;
+    if post.comments_locked {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Comments are locked on this post" })),
+        ));
+    }
+
+    if let Some(parent_id) = body.parent_id {
+        match Comment::get_by_id(parent_id, &data.db).await {
+            // replies only go to comments readers can see
+            Ok(parent)
+                if parent.post_id == post.id
+                    && parent.status == CommentStatus::Approved.as_str() =>
+            {
+                if parent.depth >= MAX_REPLY_DEPTH {
+                    return Err((
+                        StatusCode::BAD_REQUEST,
+                        Json(json!({
+                            "message": format!(
+                                "Replies can only be nested {} levels deep",
+                                MAX_REPLY_DEPTH
+                            )
+                        })),
+                    ));
+                }
+            }
+            Ok(_) | Err(sqlx::Error::RowNotFound) => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "message": "Parent comment not found" })),
+                ));
+            }
+            Err(e) => {
+                eprintln!("Error getting comment: {:?}", e);
+                return Err((
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "message": "Error getting comment" })),
+                ));
+            }
+        }
+    }
+
+    let error = |e| {
+        eprintln!("Error checking comment: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error creating comment" })),
+        )
+    };
+
+    let now = Utc::now();
+    let recent = Comment::find_times_by_user(user.id, now - data.spam.history_window(), &data.db)
+        .await
+        .map_err(error)?;
+    let verdict = data.spam.check(&CommentSample {
+        content,
+        recent: &recent,
+        now,
+    });
+
+    let trusted = is_trusted(&user, &post, None, &data.db)
+        .await
+        .map_err(error)?;
+    let status = verdict.comment_status(trusted, None);
+
+    let result = Comment::insert(
+        post.id,
+        user.id,
+        body.parent_id,
+        content,
+        status,
+        verdict.score,
+        &data.db,
+    )
+    .await;
+
+    match result {
+        Ok(comment) => Ok((StatusCode::CREATED, Json(CommentResponse::from(comment)))),
+        Err(e) => {
+            eprintln!("Error creating comment: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error creating comment" })),
+            ))
+        }
+    }
+}
+
+pub async fn update_comment_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<UpdateCommentSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let content = valid_content(&body.content)?;
+
+    // only the author may change what they wrote
+    let comment = find_comment(id, &data.db).await?;
+    if comment.user_id != user.id {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Forbidden" })),
+        ));
+    }
+
+    let post = find_published_post(comment.post_id, &data.db).await?;
+    let error = |e| {
+        eprintln!("Error checking comment: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error updating comment" })),
+        )
+    };
+
+    // edits are checked again, but the author's pace doesn't matter here. The
+    // comment itself doesn't count towards trusting its author, or getting a
+    // harmless first comment approved would let anything be edited in.
+    let verdict = data.spam.check(&CommentSample {
+        content,
+        recent: &[],
+        now: Utc::now(),
+    });
+    let trusted = is_trusted(&user, &post, Some(comment.id), &data.db)
+        .await
+        .map_err(error)?;
+    let status = verdict.comment_status(trusted, CommentStatus::parse(&comment.status));
+
+    match Comment::update(comment.id, content, status, verdict.score, &data.db).await {
+        Ok(comment) => Ok((StatusCode::OK, Json(CommentResponse::from(comment)))),
+        Err(e) => {
+            eprintln!("Error updating comment: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error updating comment" })),
+            ))
+        }
+    }
+}
+
+pub async fn delete_comment_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let comment = find_comment(id, &data.db).await?;
+    if comment.user_id != user.id && !user.can(Permission::DeleteAnyComment) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Forbidden" })),
+        ));
+    }
+
+    match Comment::soft_delete(comment.id, &data.db).await {
+        Ok(()) => Ok((
+            StatusCode::OK,
+            Json(json!({
+                "status": "success",
+                "data": { "id": comment.id }
+            })),
+        )),
+        Err(e) => {
+            eprintln!("Error deleting comment: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error deleting comment" })),
+            ))
+        }
+    }
+}
+
+/// Locks or unlocks comments on a post, for whoever may edit the post.
+pub async fn lock_comments_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<LockCommentsSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    find_editable_post(id, &user, &data.db).await?;
+
+    match Post::set_comments_locked(id, body.locked, &data.db).await {
+        Ok(post) => {
+            let post = post_responses(vec![post], Some(&user), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::OK, Json(post)))
+        }
+        Err(e) => {
+            eprintln!("Error locking comments: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error locking comments" })),
+            ))
+        }
+    }
+}
+
+/// The comments waiting for the user's decision: every comment for moderators,
+/// comments on their own posts for authors.
+pub async fn list_moderation_queue_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<ModerationQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let status = match query.status.as_deref() {
+        None => CommentStatus::Pending,
+        Some(status) => match CommentStatus::parse(status) {
+            Some(status) if status != CommentStatus::Approved => status,
+            _ => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "message": "Status must be pending, spam or rejected" })),
+                ));
+            }
+        },
+    };
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let per_page = pagination::per_page(query.per_page);
+    let post_author = if user.can(Permission::ModerateComments) {
+        None
+    } else {
+        Some(user.id)
+    };
+
+    let error = |e| {
+        eprintln!("Error getting moderation queue: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting comments" })),
+        )
+    };
+
+    let comments = Comment::find_for_moderation(
+        status,
+        post_author,
+        Cursor::key(cursor),
+        per_page + 1,
+        &data.db,
+    )
+    .await
+    .map_err(error)?;
+    let continues = pagination::continues(cursor, |after| {
+        Comment::find_for_moderation(status, post_author, after, 1, &data.db)
+    })
+    .await
+    .map_err(error)?;
+
+    let params = [
+        ("status", query.status.clone()),
+        ("per_page", query.per_page.map(|_| per_page.to_string())),
+    ];
+    let mut page = Page::keyset(
+        comments,
+        per_page,
+        cursor,
+        continues,
+        |comment| (comment.created_at, comment.id),
+        |cursor| pagination::link("/api/comments/moderation", &params, cursor),
+    );
+
+    let comments = std::mem::take(&mut page.data)
+        .into_iter()
+        .map(|comment| QueuedComment {
+            spam_score: comment.spam_score,
+            comment: CommentResponse::from(comment),
+        })
+        .collect();
+
+    Ok((StatusCode::OK, Json(page.with_data(comments))))
+}
+
+/// Approves, rejects or marks a comment as spam. Editors moderate every
+/// comment, authors the comments on their own posts.
+pub async fn moderate_comment_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Json(body): Json<ModerateCommentSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let status = match body.action.as_str() {
+        "approve" => CommentStatus::Approved,
+        "reject" => CommentStatus::Rejected,
+        "spam" => CommentStatus::Spam,
+        _ => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "message": "Action must be approve, reject or spam" })),
+            ));
+        }
+    };
+
+    let comment = find_comment(id, &data.db).await?;
+    let post_author = match Post::get_by_id(comment.post_id, &data.db).await {
+        Ok(post) => Some(post.user_id),
+        Err(sqlx::Error::RowNotFound) => None,
+        Err(e) => {
+            eprintln!("Error getting post: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting post" })),
+            ));
+        }
+    };
+    if !user.can(Permission::ModerateComments) && post_author != Some(user.id) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Forbidden" })),
+        ));
+    }
+
+    match Comment::moderate(comment.id, status, user.id, &data.db).await {
+        Ok(comment) => Ok((StatusCode::OK, Json(CommentResponse::from(comment)))),
+        Err(e) => {
+            eprintln!("Error moderating comment: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error moderating comment" })),
+            ))
+        }
+    }
+}
+
+fn valid_content(content: &str) -> Result<&str, (StatusCode, Json<serde_json::Value>)> {
+    let content = content.trim();
+    if content.is_empty() || content.chars().count() > MAX_COMMENT_LENGTH {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "message": format!(
+                    "Comments must be between 1 and {} characters",
+                    MAX_COMMENT_LENGTH
+                )
+            })),
+        ));
+    }
+    Ok(content)
+}
+
+/// Moderators and the post's author skip the queue, everyone else needs a
+/// verified email and an earlier approved comment other than `except`.
+async fn is_trusted(
+    user: &User,
+    post: &Post,
+    except: Option<Uuid>,
+    db: &sqlx::PgPool,
+) -> Result<bool, sqlx::Error> {
+    if user.can(Permission::ModerateComments) || post.user_id == user.id {
+        return Ok(true);
+    }
+
+    Ok(user.verified && Comment::has_approved_by_user(user.id, except, db).await?)
+}
+
+async fn find_comment(
+    id: Uuid,
+    db: &sqlx::PgPool,
+) -> Result<Comment, (StatusCode, Json<serde_json::Value>)> {
+    match Comment::get_by_id(id, db).await {
+        Ok(comment) => Ok(comment),
+        Err(sqlx::Error::RowNotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Comment not found" })),
+        )),
+        Err(e) => {
+            eprintln!("Error getting comment: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting comment" })),
+            ))
+        }
+    }
+}
+
+/// Nests the replies under the top level comments they belong to. Works from
+/// the deepest replies up, so each reply already holds its own replies when
+/// it moves under its parent.
+fn thread(roots: Vec<Comment>, mut replies: Vec<Comment>) -> Vec<CommentResponse> {
+    // the sort is stable, siblings stay oldest first
+    replies.sort_by_key(|reply| std::cmp::Reverse(reply.depth));
+    let moves: Vec<(Uuid, Option<Uuid>)> = replies
+        .iter()
+        .map(|reply| (reply.id, reply.parent_id))
+        .collect();
+    let root_ids: Vec<Uuid> = roots.iter().map(|root| root.id).collect();
+
+    let mut nodes: HashMap<Uuid, CommentResponse> = roots
+        .into_iter()
+        .chain(replies)
+        .map(|comment| {
+            let id = comment.id;
+            let node = CommentResponse {
+                replies: Some(Vec::new()),
+                ..CommentResponse::from(comment)
+            };
+            (id, node)
+        })
+        .collect();
+
+    for (id, parent_id) in moves {
+        // replies whose parent isn't shown are left out
+        let (Some(node), Some(parent_id)) = (nodes.remove(&id), parent_id) else {
+            continue;
+        };
+        if let Some(parent) = nodes.get_mut(&parent_id) {
+            parent.replies.get_or_insert_with(Vec::new).push(node);
+        }
+    }
+
+    root_ids.iter().filter_map(|id| nodes.remove(id)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A comment created `minute` minutes into the thread, `parent` is the id
+    /// and depth of the comment it replies to. Replies are listed oldest first,
+    /// like `Comment::find_replies` returns them.
+    fn comment(id: u128, parent: Option<(u128, i32)>, minute: i64) -> Comment {
+        let created_at =
+            Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap() + chrono::Duration::minutes(minute);
+        Comment {
+            id: Uuid::from_u128(id),
+            post_id: Uuid::nil(),
+            user_id: Uuid::nil(),
+            parent_id: parent.map(|(parent, _)| Uuid::from_u128(parent)),
+            depth: parent.map_or(0, |(_, depth)| depth + 1),
+            content: format!("comment {}", id),
+            content_html: format!("<p>comment {}</p>", id),
+            status: CommentStatus::Approved.as_str().to_string(),
+            spam_score: 0.0,
+            author_name: "author".to_string(),
+            author_photo: String::new(),
+            created_at,
+            updated_at: created_at,
+            deleted_at: None,
+        }
+    }
+
+    /// The thread as `1(2 3(4))`, replies in parentheses.
+    fn outline(comments: &[CommentResponse]) -> String {
+        comments
+            .iter()
+            .map(|comment| {
+                let id = comment.id.as_u128().to_string();
+                match comment.replies.as_deref() {
+                    Some([]) | None => id,
+                    Some(replies) => format!("{}({})", id, outline(replies)),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn nests_replies_several_levels_deep() {
+        let roots = vec![comment(1, None, 0), comment(2, None, 1)];
+        let replies = vec![
+            comment(10, Some((1, 0)), 2),
+            comment(11, Some((10, 1)), 3),
+            comment(12, Some((11, 2)), 4),
+            comment(13, Some((12, 3)), 5),
+            comment(20, Some((2, 0)), 6),
+        ];
+
+        assert_eq!(outline(&thread(roots, replies)), "1(10(11(12(13)))) 2(20)");
+    }
+
+    #[test]
+    fn keeps_siblings_oldest_first() {
+        let roots = vec![
+            comment(1, None, 0),
+            comment(2, None, 1),
+            comment(3, None, 2),
+        ];
+        let replies = vec![
+            comment(10, Some((1, 0)), 3),
+            comment(11, Some((10, 1)), 4),
+            comment(12, Some((1, 0)), 5),
+            comment(13, Some((10, 1)), 6),
+            comment(14, Some((3, 0)), 7),
+            comment(15, Some((1, 0)), 8),
+            comment(16, Some((12, 1)), 9),
+            comment(17, Some((10, 1)), 10),
+        ];
+
+        assert_eq!(
+            outline(&thread(roots, replies)),
+            "1(10(11 13 17) 12(16) 15) 2 3(14)"
+        );
+    }
+
+    #[test]
+    fn leaves_out_replies_without_a_shown_parent() {
+        let roots = vec![comment(1, None, 0)];
+        // 10 is pending and 30 replies to a root on another page, neither
+        // was fetched, so their replies have nowhere to go
+        let replies = vec![
+            comment(11, Some((10, 1)), 2),
+            comment(12, Some((11, 2)), 3),
+            comment(20, Some((1, 0)), 4),
+            comment(31, Some((30, 0)), 5),
+        ];
+
+        assert_eq!(outline(&thread(roots, replies)), "1(20)");
+        assert_eq!(
+            outline(&thread(vec![], vec![comment(20, Some((1, 0)), 1)])),
+            ""
+        );
+    }
+
+    #[test]
+    fn every_comment_has_a_replies_list() {
+        let threaded = thread(vec![comment(1, None, 0)], vec![comment(2, Some((1, 0)), 1)]);
+        assert_eq!(threaded[0].replies.as_ref().map(Vec::len), Some(1));
+        assert_eq!(
+            threaded[0].replies.as_ref().unwrap()[0]
+                .replies
+                .as_ref()
+                .map(Vec::len),
+            Some(0)
+        );
+    }
+}
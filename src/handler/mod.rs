@@ -1,2 +1,8 @@
 pub mod user;
-pub mod post;
\ No newline at end of file
+pub mod post;
+pub mod admin;
+pub mod revision;
+pub mod taxonomy;
+pub mod comment;
+pub mod reaction;
+pub mod bookmark;
\ No newline at end of file
@@ -2,31 +2,57 @@ use std::sync::Arc;
 
 use axum::{
     extract::{Path, Query, State},
-    http::{header, Response, StatusCode},
+    http::{header, StatusCode},
     response::IntoResponse,
     Extension, Json,
 };
+use chrono::prelude::*;
 use serde_json::json;
 use uuid::Uuid;
 
 use crate::{
+    model::bookmark::Bookmark,
+    model::comment::Comment,
     model::post::{
-        CreatePostSchema, DeletePostSchema, GetPostsPaginatedSchema, Post, UpdatePostSchema,
+        ContentFormat, CreatePostSchema, DeletePostQuery, DeletePostSchema, Drafts,
+        GetPostsPaginatedSchema, Post, PostStatus, SearchMatch, SearchPostsQuery, UpdatePostSchema,
+        UserPostsQuery,
     },
-    model::{post, user::User},
-    response::FilteredUser,
+    model::reaction::{PostReaction, ReactionCount},
+    model::taxonomy::{same_name, Category, PostTaxonomy, Tag},
+    model::user::User,
+    pagination::{self, Cursor, Page},
+    rbac::{require, Authorized, Permission},
+    render,
+    response::{AuthorSummary, PostResponse, SearchResult},
+    slug::{slugify, slugify_name},
     AppState,
 };
 
+const MAX_TAGS: usize = 20;
+
 pub async fn get_post_handler(
+    user: Option<Extension<User>>,
     State(data): State<Arc<AppState>>,
     Path(id): Path<Uuid>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    // get the post from the database and check if it exists and if the user is the owner
+    // get the post from the database and check if it exists, drafts are only
+    // shown to whoever may edit them
     let post = Post::get_by_id(id, &data.db).await;
 
     match post {
-        Ok(post) => Ok((StatusCode::OK, Json(post))),
+        Ok(post)
+            if post.is_published() || user.as_deref().is_some_and(|user| can_edit(user, &post)) =>
+        {
+            let post = post_responses(vec![post], user.as_deref(), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::OK, Json(post)))
+        }
+        Ok(_) | Err(sqlx::Error::RowNotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Post not found" })),
+        )),
         Err(e) => {
             eprintln!("Error getting post: {:?}", e);
             Err((
@@ -38,50 +64,119 @@ pub async fn get_post_handler(
 }
 
 pub async fn get_posts_handler(
+    user: Option<Extension<User>>,
     State(data): State<Arc<AppState>>,
     Query(query): Query<GetPostsPaginatedSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let page = match query.page {
-        Some(page) => page,
-        None => 1,
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let per_page = pagination::per_page(query.per_page);
+    let tag = query.tag.as_deref();
+    let category = query.category.as_deref();
+    let error = |e| {
+        eprintln!("Error getting posts: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting posts" })),
+        )
     };
 
-    let per_page = match query.per_page {
-        Some(per_page) => per_page,
-        None => 10,
+    let drafts = match (query.include_drafts, user.as_deref()) {
+        (Some(true), Some(user)) => editable_drafts(user),
+        (Some(true), None) => {
+            return Err((
+                StatusCode::UNAUTHORIZED,
+                Json(json!({ "message": "You are not logged in, please provide token" })),
+            ));
+        }
+        _ => None,
     };
+    if drafts.is_some() && query.sort.as_deref() == Some("popular") {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Drafts can only be listed newest first" })),
+        ));
+    }
 
-    // Get the posts from the database
-    let posts = Post::find_all(&data.db, page, per_page).await;
+    let total = match query.include_total {
+        Some(true) => Some(
+            Post::count_all(tag, category, drafts, &data.db)
+                .await
+                .map_err(error)?,
+        ),
+        _ => None,
+    };
 
-    // Check if the posts were found
-    match posts {
-        Ok(posts) => {
-            let posts: Vec<Post> = posts
-                .into_iter()
-                .map(|mut post| {
-                    post.user_id = uuid::Uuid::nil();
-                    post
-                })
-                .collect();
+    let params = [
+        ("tag", query.tag.clone()),
+        ("category", query.category.clone()),
+        ("sort", query.sort.clone()),
+        ("per_page", query.per_page.map(|_| per_page.to_string())),
+        ("include_total", query.include_total.map(|b| b.to_string())),
+        (
+            "include_drafts",
+            query.include_drafts.map(|b| b.to_string()),
+        ),
+    ];
+    let link = |cursor: Option<&str>| pagination::link("/api/posts", &params, cursor);
 
-            Ok((StatusCode::OK, Json(posts)))
+    // Get the posts from the database, one extra to know if there is a next page
+    let mut page = match (query.sort.as_deref(), cursor) {
+        (None | Some("newest"), None | Some(Cursor::Key { .. })) => {
+            let posts = Post::find_all(
+                tag,
+                category,
+                drafts,
+                Cursor::key(cursor),
+                per_page + 1,
+                &data.db,
+            )
+            .await
+            .map_err(error)?;
+            let continues = pagination::continues(cursor, |after| {
+                Post::find_all(tag, category, drafts, after, 1, &data.db)
+            })
+            .await
+            .map_err(error)?;
+            Page::keyset(posts, per_page, cursor, continues, Post::page_key, link)
         }
-        Err(e) => {
-            eprintln!("Error getting posts: {:?}", e);
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "message": "Error getting posts" })),
-            ))
+        // popularity shifts between requests, so there is no stable key to page on
+        (Some("popular"), None | Some(Cursor::Offset(_))) => {
+            let offset = match cursor {
+                Some(Cursor::Offset(offset)) => offset,
+                _ => 0,
+            };
+            let posts = Post::find_popular(tag, category, offset, per_page + 1, &data.db)
+                .await
+                .map_err(error)?;
+            Page::offset(posts, per_page, offset, link)
+        }
+        (None | Some("newest" | "popular"), _) => return Err(invalid_cursor()),
+        (Some(_), _) => {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                Json(json!({ "message": "Sort must be newest or popular" })),
+            ));
         }
     }
+    .with_total(total);
+
+    let posts = post_responses(std::mem::take(&mut page.data), user.as_deref(), &data.db).await?;
+
+    Ok((StatusCode::OK, Json(page.with_data(posts))))
 }
 
 pub async fn create_post_handler(
-    Extension(user): Extension<User>,
+    Authorized(user, _): Authorized<require::CreatePost>,
     State(data): State<Arc<AppState>>,
     Json(body): Json<CreatePostSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if !user.verified {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Please verify your email before creating posts" })),
+        ));
+    }
+
     // check if body is valid
     if body.title.is_empty() || body.content.is_empty() || body.photo.is_empty() {
         return Err((
@@ -90,26 +185,51 @@ pub async fn create_post_handler(
         ));
     }
 
-    let slug = create_slug(&body.title);
+    let (status, published_at) = publication(body.status.as_deref(), body.published_at, None)
+        .map_err(|message| (StatusCode::BAD_REQUEST, Json(json!({ "message": message }))))?;
+    let content_format = content_format(body.content_format.as_deref(), None)?;
+    let language = search_language(body.language, None, &data)?;
+    let taxonomy = resolve_taxonomy(body.tags, body.categories, &data.db).await?;
+    let base_slug = slugify(&body.title);
 
     // Access the request body using the `body` variable
     let post = Post {
         id: uuid::Uuid::new_v4(),
         title: body.title,
-        slug: Some(slug),
+        slug: None,
         content: body.content,
+        content_format: content_format.to_string(),
+        content_html: None,
+        comments_locked: false,
         photo: body.photo,
         user_id: user.id,
+        status: status.to_string(),
+        published_at,
+        language,
         created_at: None,
         updated_at: None,
+        deleted_at: None,
     };
 
-    // Insert the post into the database
-    let result = Post::insert(post, &data.db).await;
+    // Insert the post into the database, another post may take the same slug
+    // between the lookup and the insert so retry a few times
+    let mut result = Err(sqlx::Error::RowNotFound);
+    for _ in 0..3 {
+        result = insert_post(&post, &base_slug, &taxonomy, &data.db).await;
+
+        if !matches!(&result, Err(e) if is_unique_violation(e, "posts_slug_key")) {
+            break;
+        }
+    }
 
     // Check if the post was inserted successfully
     match result {
-        Ok(post) => Ok((StatusCode::CREATED, Json(post))),
+        Ok(post) => {
+            let post = post_responses(vec![post], Some(&user), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::CREATED, Json(post)))
+        }
         Err(e) => {
             eprintln!("Error creating post: {:?}", e);
             Err((
@@ -134,26 +254,21 @@ pub async fn update_post_handler(
         ));
     }
 
-    // get the post from the database and check if it exists and if the user is the owner
-    let post = Post::get_by_id(body.id, &data.db).await;
+    let existing = find_editable_post(body.id, &user, &data.db).await?;
 
-    match post {
-        Ok(post) => {
-            if post.user_id != user.id {
-                return Err((
-                    StatusCode::UNAUTHORIZED,
-                    Json(json!({ "message": "Unauthorized" })),
-                ));
-            }
-        }
-        Err(e) => {
-            eprintln!("Error getting post: {:?}", e);
-            return Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({ "message": "Error getting post" })),
-            ));
-        }
-    }
+    // the slug follows the title, the old one keeps redirecting to the post
+    let base_slug = if body.title != existing.title {
+        Some(slugify(&body.title))
+    } else {
+        None
+    };
+
+    let (status, published_at) =
+        publication(body.status.as_deref(), body.published_at, Some(&existing))
+            .map_err(|message| (StatusCode::BAD_REQUEST, Json(json!({ "message": message }))))?;
+    let content_format = content_format(body.content_format.as_deref(), Some(&existing))?;
+    let language = search_language(body.language, Some(&existing), &data)?;
+    let taxonomy = resolve_taxonomy(body.tags, body.categories, &data.db).await?;
 
     // Access the request body using the `body` variable
     let post = Post {
@@ -161,18 +276,30 @@ pub async fn update_post_handler(
         title: body.title,
         slug: None,
         content: body.content,
+        content_format: content_format.to_string(),
+        content_html: None,
+        comments_locked: existing.comments_locked,
         photo: body.photo,
-        user_id: user.id,
+        user_id: existing.user_id,
+        status: status.to_string(),
+        published_at,
+        language,
         created_at: None,
         updated_at: None,
+        deleted_at: None,
     };
 
     // Update the post in the database
-    let result = Post::update(post, &data.db).await;
+    let result = save_post(post, base_slug, &taxonomy, user.id, &data.db).await;
 
     // Check if the post was updated successfully
     match result {
-        Ok(post) => Ok((StatusCode::OK, Json(post))),
+        Ok(post) => {
+            let post = post_responses(vec![post], Some(&user), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::OK, Json(post)))
+        }
         Err(e) => {
             eprintln!("Error updating post: {:?}", e);
             Err((
@@ -183,37 +310,783 @@ pub async fn update_post_handler(
     }
 }
 
-pub fn create_slug(title: &str) -> String {
-    let slug = title
-        .to_lowercase()
-        .replace(" ", "-")
-        .replace("?", "")
-        .replace("!", "")
-        .replace(".", "")
-        .replace(",", "")
-        .replace(":", "")
-        .replace(";", "")
-        .replace("(", "")
-        .replace(")", "")
-        .replace("[", "")
-        .replace("]", "")
-        .replace("{", "")
-        .replace("}", "")
-        .replace("*", "")
-        .replace("&", "")
-        .replace("@", "")
-        .replace("#", "")
-        .replace("$", "")
-        .replace("%", "")
-        .replace("^", "")
-        .replace("+", "")
-        .replace("=", "")
-        .replace("`", "")
-        .replace("~", "")
-        .replace("<", "")
-        .replace(">", "")
-        .replace("/", "")
-        .replace("|", "");
-
-    slug
+pub async fn delete_post_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(params): Path<DeletePostSchema>,
+    Query(query): Query<DeletePostQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // soft deleted posts are kept around, only editors can remove them for good
+    let hard = query.hard.unwrap_or(false);
+
+    // get the post from the database and check if it exists and if the user is the owner,
+    // posts that are already soft deleted can still be purged
+    let post = if hard {
+        Post::get_by_id_including_deleted(params.id, &data.db).await
+    } else {
+        Post::get_by_id(params.id, &data.db).await
+    };
+    let post = match post {
+        Ok(post) => post,
+        Err(sqlx::Error::RowNotFound) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "message": "Post not found" })),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error getting post: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting post" })),
+            ));
+        }
+    };
+
+    // editors may delete anyone's post, authors only their own
+    let allowed = user.can(Permission::DeleteAnyPost)
+        || (post.user_id == user.id && user.can(Permission::DeleteOwnPost));
+    if !allowed {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Forbidden" })),
+        ));
+    }
+
+    if hard && !user.can(Permission::DeleteAnyPost) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Only editors can permanently delete posts" })),
+        ));
+    }
+
+    let result = if hard {
+        Post::delete(post.id, &data.db).await
+    } else {
+        Post::soft_delete(post.id, &data.db).await
+    };
+
+    match result {
+        Ok(post) => Ok((
+            StatusCode::OK,
+            Json(json!({
+                "status": "success",
+                "data": {
+                    "id": post.id,
+                    "deleted": if hard { "hard" } else { "soft" },
+                }
+            })),
+        )),
+        Err(e) => {
+            eprintln!("Error deleting post: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error deleting post" })),
+            ))
+        }
+    }
+}
+
+pub async fn get_post_by_slug_handler(
+    user: Option<Extension<User>>,
+    State(data): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let error = |e| {
+        eprintln!("Error getting post: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting post" })),
+        )
+    };
+
+    // drafts are only shown to whoever may edit them, like on `get_post_handler`
+    let post = match Post::get_by_slug(&slug, &data.db).await {
+        Ok(post)
+            if !post.is_published()
+                && !user.as_deref().is_some_and(|user| can_edit(user, &post)) =>
+        {
+            Err(sqlx::Error::RowNotFound)
+        }
+        post => post,
+    };
+
+    match post {
+        Ok(post) => {
+            let post = post_responses(vec![post], user.as_deref(), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::OK, Json(post)).into_response())
+        }
+        Err(sqlx::Error::RowNotFound) => {
+            // old slugs permanently redirect to the current one
+            match Post::get_slug_redirect(&slug, &data.db)
+                .await
+                .map_err(error)?
+            {
+                Some(canonical) => Ok((
+                    StatusCode::MOVED_PERMANENTLY,
+                    [(
+                        header::LOCATION,
+                        format!("/api/posts/by-slug/{}", urlencoding::encode(&canonical)),
+                    )],
+                    Json(json!({ "slug": canonical })),
+                )
+                    .into_response()),
+                None => Err((
+                    StatusCode::NOT_FOUND,
+                    Json(json!({ "message": "Post not found" })),
+                )),
+            }
+        }
+        Err(e) => Err(error(e)),
+    }
+}
+
+pub async fn search_posts_handler(
+    user: Option<Extension<User>>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<SearchPostsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let q = query.q.trim();
+    if q.is_empty() || q.chars().count() > 200 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Search query must be between 1 and 200 characters" })),
+        ));
+    }
+
+    let languages = match &query.lang {
+        Some(lang) => vec![search_language(Some(lang.to_owned()), None, &data)?],
+        None => data.env.search_languages.clone(),
+    };
+    let offset = match parse_cursor(query.cursor.as_deref())? {
+        Some(Cursor::Offset(offset)) => offset,
+        Some(_) => return Err(invalid_cursor()),
+        None => 0,
+    };
+    let per_page = pagination::per_page(query.per_page);
+
+    let matches = match Post::search(q, &languages, offset, per_page + 1, &data.db).await {
+        Ok(matches) => matches,
+        Err(e) => {
+            eprintln!("Error searching posts: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error searching posts" })),
+            ));
+        }
+    };
+
+    let params = [
+        ("q", Some(q.to_string())),
+        ("lang", query.lang.clone()),
+        ("per_page", query.per_page.map(|_| per_page.to_string())),
+    ];
+    let mut page = Page::offset(matches, per_page, offset, |cursor| {
+        pagination::link("/api/posts/search", &params, cursor)
+    });
+
+    let (posts, matches): (Vec<Post>, Vec<SearchMatch>) =
+        std::mem::take(&mut page.data).into_iter().unzip();
+    let results: Vec<SearchResult> = post_responses(posts, user.as_deref(), &data.db)
+        .await?
+        .into_iter()
+        .zip(matches)
+        .map(|(post, search)| SearchResult { post, search })
+        .collect();
+
+    Ok((StatusCode::OK, Json(page.with_data(results))))
+}
+
+pub async fn get_my_posts_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Query(query): Query<UserPostsQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let status = match query.status.as_deref() {
+        Some(status) => Some(PostStatus::parse(status).ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Invalid status" })),
+        ))?),
+        None => None,
+    };
+    let cursor = parse_cursor(query.cursor.as_deref())?;
+    let per_page = pagination::per_page(query.per_page);
+    let error = |e| {
+        eprintln!("Error getting posts: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting posts" })),
+        )
+    };
+
+    let posts = Post::find_by_user(user.id, status, Cursor::key(cursor), per_page + 1, &data.db)
+        .await
+        .map_err(error)?;
+    let continues = pagination::continues(cursor, |after| {
+        Post::find_by_user(user.id, status, after, 1, &data.db)
+    })
+    .await
+    .map_err(error)?;
+
+    let total = match query.include_total {
+        Some(true) => Some(
+            Post::count_by_user(user.id, status, &data.db)
+                .await
+                .map_err(error)?,
+        ),
+        _ => None,
+    };
+
+    let params = [
+        ("status", query.status.clone()),
+        ("per_page", query.per_page.map(|_| per_page.to_string())),
+        ("include_total", query.include_total.map(|b| b.to_string())),
+    ];
+    let mut page = Page::keyset(
+        posts,
+        per_page,
+        cursor,
+        continues,
+        Post::page_key,
+        |cursor| pagination::link("/api/users/me/posts", &params, cursor),
+    )
+    .with_total(total);
+
+    let posts = post_responses(std::mem::take(&mut page.data), Some(&user), &data.db).await?;
+
+    Ok((StatusCode::OK, Json(page.with_data(posts))))
+}
+
+pub async fn highlight_css_handler() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/css; charset=utf-8")],
+        render::highlight_css(),
+    )
+}
+
+pub fn parse_cursor(
+    cursor: Option<&str>,
+) -> Result<Option<Cursor>, (StatusCode, Json<serde_json::Value>)> {
+    match cursor {
+        Some(cursor) => Cursor::decode(cursor).map(Some).ok_or_else(invalid_cursor),
+        None => Ok(None),
+    }
+}
+
+fn invalid_cursor() -> (StatusCode, Json<serde_json::Value>) {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(json!({ "message": "Invalid cursor" })),
+    )
+}
+
+/// Fetches a post readers can see, drafts and deleted posts are not found.
+pub async fn find_published_post(
+    id: Uuid,
+    db: &sqlx::PgPool,
+) -> Result<Post, (StatusCode, Json<serde_json::Value>)> {
+    match Post::get_by_id(id, db).await {
+        Ok(post) if post.is_published() => Ok(post),
+        Ok(_) | Err(sqlx::Error::RowNotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": "Post not found" })),
+        )),
+        Err(e) => {
+            eprintln!("Error getting post: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting post" })),
+            ))
+        }
+    }
+}
+
+/// Fetches a post the user is allowed to edit: editors may edit anyone's post,
+/// authors only their own.
+pub async fn find_editable_post(
+    id: Uuid,
+    user: &User,
+    db: &sqlx::PgPool,
+) -> Result<Post, (StatusCode, Json<serde_json::Value>)> {
+    let post = match Post::get_by_id(id, db).await {
+        Ok(post) => post,
+        Err(sqlx::Error::RowNotFound) => {
+            return Err((
+                StatusCode::NOT_FOUND,
+                Json(json!({ "message": "Post not found" })),
+            ));
+        }
+        Err(e) => {
+            eprintln!("Error getting post: {:?}", e);
+            return Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting post" })),
+            ));
+        }
+    };
+
+    if !can_edit(user, &post) {
+        return Err((
+            StatusCode::FORBIDDEN,
+            Json(json!({ "message": "Forbidden" })),
+        ));
+    }
+
+    Ok(post)
+}
+
+/// Whether the user may edit the post, see `find_editable_post`.
+fn can_edit(user: &User, post: &Post) -> bool {
+    user.can(Permission::UpdateAnyPost)
+        || (post.user_id == user.id && user.can(Permission::UpdateOwnPost))
+}
+
+/// Which drafts the user may edit, see `can_edit`.
+fn editable_drafts(user: &User) -> Option<Drafts> {
+    if user.can(Permission::UpdateAnyPost) {
+        Some(Drafts::All)
+    } else if user.can(Permission::UpdateOwnPost) {
+        Some(Drafts::Own(user.id))
+    } else {
+        None
+    }
+}
+
+/// Saves an edited post. When `base_slug` is set the post gets the first free
+/// slug derived from it, retrying if another post grabs that slug first.
+pub async fn save_post(
+    post: Post,
+    base_slug: Option<String>,
+    taxonomy: &PostTaxonomy,
+    editor_id: Uuid,
+    db: &sqlx::PgPool,
+) -> Result<Post, sqlx::Error> {
+    let mut result = Err(sqlx::Error::RowNotFound);
+    for _ in 0..3 {
+        let slug = match &base_slug {
+            Some(base_slug) => Some(Post::next_available_slug(base_slug, post.id, db).await?),
+            None => None,
+        };
+
+        result = async {
+            let mut tx = db.begin().await?;
+            let post = Post::update(
+                Post {
+                    slug,
+                    ..post.clone()
+                },
+                editor_id,
+                &mut tx,
+            )
+            .await?;
+            taxonomy.apply(post.id, &mut tx).await?;
+            tx.commit().await?;
+            Ok(post)
+        }
+        .await;
+
+        if !matches!(&result, Err(e) if is_unique_violation(e, "posts_slug_key")) {
+            break;
+        }
+    }
+
+    result
+}
+
+async fn insert_post(
+    post: &Post,
+    base_slug: &str,
+    taxonomy: &PostTaxonomy,
+    db: &sqlx::PgPool,
+) -> Result<Post, sqlx::Error> {
+    let slug = Post::next_available_slug(base_slug, post.id, db).await?;
+
+    let mut tx = db.begin().await?;
+    let post = Post::insert(
+        Post {
+            slug: Some(slug),
+            ..post.clone()
+        },
+        &mut tx,
+    )
+    .await?;
+    taxonomy.apply(post.id, &mut tx).await?;
+    tx.commit().await?;
+
+    Ok(post)
+}
+
+/// Checks the requested search language against `SEARCH_LANGUAGES`, defaulting
+/// to the post's current language or the first configured one.
+fn search_language(
+    language: Option<String>,
+    existing: Option<&Post>,
+    data: &AppState,
+) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    let languages = &data.env.search_languages;
+    match language {
+        Some(language) => {
+            let language = language.to_lowercase();
+            if !languages.contains(&language) {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({
+                        "message": format!("Language must be one of: {}", languages.join(", "))
+                    })),
+                ));
+            }
+            Ok(language)
+        }
+        None => Ok(existing
+            .map(|post| post.language.clone())
+            .unwrap_or_else(|| languages[0].clone())),
+    }
+}
+
+/// Parses the requested content format, defaulting to the post's current one
+/// or Markdown.
+fn content_format(
+    format: Option<&str>,
+    existing: Option<&Post>,
+) -> Result<ContentFormat, (StatusCode, Json<serde_json::Value>)> {
+    match format {
+        Some(format) => ContentFormat::parse(format).ok_or((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Content format must be markdown or html" })),
+        )),
+        None => Ok(existing
+            .and_then(|post| ContentFormat::parse(&post.content_format))
+            .unwrap_or(ContentFormat::Markdown)),
+    }
+}
+
+/// Validates the tags and category slugs sent with a post.
+async fn resolve_taxonomy(
+    tags: Option<Vec<String>>,
+    categories: Option<Vec<String>>,
+    db: &sqlx::PgPool,
+) -> Result<PostTaxonomy, (StatusCode, Json<serde_json::Value>)> {
+    let bad_request =
+        |message: String| (StatusCode::BAD_REQUEST, Json(json!({ "message": message })));
+
+    if let Some(tags) = &tags {
+        if tags.len() > MAX_TAGS {
+            return Err(bad_request(format!(
+                "A post can have at most {} tags",
+                MAX_TAGS
+            )));
+        }
+        if tags
+            .iter()
+            .any(|tag| tag.trim().is_empty() || tag.trim().chars().count() > 50)
+        {
+            return Err(bad_request(
+                "Tags must be between 1 and 50 characters".to_string(),
+            ));
+        }
+
+        let mut slugs: Vec<(&str, String)> = Vec::new();
+        for tag in tags {
+            let name = tag.trim();
+            let slug = slugify_name(name);
+            if slug.is_empty() {
+                return Err(bad_request(format!(
+                    "Tag {} needs at least one letter or digit",
+                    name
+                )));
+            }
+            if let Some((other, _)) = slugs
+                .iter()
+                .find(|(other, other_slug)| *other_slug == slug && !same_name(other, name))
+            {
+                return Err(bad_request(format!(
+                    "Tags {} and {} would have the same slug {}",
+                    other, name, slug
+                )));
+            }
+            slugs.push((name, slug));
+        }
+
+        let (names, slugs): (Vec<&str>, Vec<String>) = slugs.into_iter().unzip();
+        let existing = Tag::find_by_slugs(&slugs, db).await.map_err(|e| {
+            eprintln!("Error getting tags: {:?}", e);
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting tags" })),
+            )
+        })?;
+        for (name, slug) in names.iter().zip(&slugs) {
+            if let Some(tag) = existing
+                .iter()
+                .find(|tag| &tag.slug == slug && !same_name(&tag.name, name))
+            {
+                return Err(bad_request(format!(
+                    "Tag {} would have the same slug {} as the tag {}",
+                    name, slug, tag.name
+                )));
+            }
+        }
+    }
+
+    let category_ids = match categories {
+        Some(slugs) => {
+            let found = Category::find_by_slugs(&slugs, db).await.map_err(|e| {
+                eprintln!("Error getting categories: {:?}", e);
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    Json(json!({ "message": "Error getting categories" })),
+                )
+            })?;
+            if let Some(missing) = slugs
+                .iter()
+                .find(|slug| !found.iter().any(|category| &category.slug == *slug))
+            {
+                return Err(bad_request(format!("Unknown category: {}", missing)));
+            }
+            Some(found.into_iter().map(|category| category.id).collect())
+        }
+        None => None,
+    };
+
+    Ok(PostTaxonomy { tags, category_ids })
+}
+
+/// Builds the public representation of the posts, loading their authors, tags,
+/// categories, comment counts and reaction counts with one query each. With a
+/// `viewer` the posts also say whether they bookmarked them and how they
+/// reacted.
+pub async fn post_responses(
+    posts: Vec<Post>,
+    viewer: Option<&User>,
+    db: &sqlx::PgPool,
+) -> Result<Vec<PostResponse>, (StatusCode, Json<serde_json::Value>)> {
+    let ids: Vec<Uuid> = posts.iter().map(|post| post.id).collect();
+    let error = |e| {
+        eprintln!("Error getting post details: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error getting posts" })),
+        )
+    };
+    let authors = Post::find_authors(&ids, db).await.map_err(error)?;
+    let tags = Tag::find_for_posts(&ids, db).await.map_err(error)?;
+    let categories = Category::find_for_posts(&ids, db).await.map_err(error)?;
+    let comment_counts = Comment::count_for_posts(&ids, db).await.map_err(error)?;
+    let reaction_counts = ReactionCount::find_for_posts(&ids, db)
+        .await
+        .map_err(error)?;
+    let (bookmarked, reacted) = match viewer {
+        Some(user) => (
+            Some(
+                Bookmark::find_bookmarked(user.id, &ids, db)
+                    .await
+                    .map_err(error)?,
+            ),
+            Some(
+                PostReaction::find_for_user(user.id, &ids, db)
+                    .await
+                    .map_err(error)?,
+            ),
+        ),
+        None => (None, None),
+    };
+
+    // posts.user_id cascades, every post has an author
+    Ok(posts
+        .into_iter()
+        .filter_map(|post| {
+            let author = authors.iter().find(|author| author.post_id == post.id)?;
+            let author = AuthorSummary {
+                id: author.id.to_string(),
+                name: author.name.to_owned(),
+                photo: author.photo.to_owned(),
+            };
+            let tags = tags
+                .iter()
+                .filter(|(post_id, _)| *post_id == post.id)
+                .map(|(_, tag)| tag.clone())
+                .collect();
+            let categories = categories
+                .iter()
+                .filter(|(post_id, _)| *post_id == post.id)
+                .map(|(_, category)| category.clone())
+                .collect();
+            let comment_count = comment_counts
+                .iter()
+                .find(|(post_id, _)| *post_id == post.id)
+                .map_or(0, |(_, count)| *count);
+            let reactions = reaction_counts
+                .iter()
+                .filter(|count| count.post_id == post.id)
+                .map(|count| (count.reaction.clone(), count.count))
+                .collect();
+            let is_bookmarked = bookmarked.as_ref().map(|ids| ids.contains(&post.id));
+            let my_reactions = reacted.as_ref().map(|reacted| {
+                reacted
+                    .iter()
+                    .filter(|(post_id, _)| *post_id == post.id)
+                    .map(|(_, reaction)| reaction.clone())
+                    .collect()
+            });
+            let mut response =
+                PostResponse::new(post, author, tags, categories, comment_count, reactions);
+            response.is_bookmarked = is_bookmarked;
+            response.my_reactions = my_reactions;
+            Some(response)
+        })
+        .collect())
+}
+
+/// Works out the status and publication date of a post from the request,
+/// falling back to what the post already has.
+fn publication(
+    status: Option<&str>,
+    published_at: Option<DateTime<Utc>>,
+    existing: Option<&Post>,
+) -> Result<(PostStatus, Option<DateTime<Utc>>), &'static str> {
+    let status = match status {
+        Some(status) => PostStatus::parse(status).ok_or("Invalid status")?,
+        None => existing
+            .and_then(|post| PostStatus::parse(&post.status))
+            .unwrap_or(PostStatus::Draft),
+    };
+
+    let now = Utc::now();
+    let previous = existing.and_then(|post| post.published_at);
+    let published_at = match status {
+        PostStatus::Draft => None,
+        PostStatus::Scheduled => match published_at.or(previous) {
+            Some(at) if at > now => Some(at),
+            _ => return Err("Scheduled posts need a published_at in the future"),
+        },
+        PostStatus::Published => {
+            let at = published_at
+                .or(previous.filter(|at| *at <= now))
+                .unwrap_or(now);
+            if at > now {
+                return Err("Use the scheduled status to publish in the future");
+            }
+            Some(at)
+        }
+        PostStatus::Archived => published_at.or(previous),
+    };
+
+    Ok((status, published_at))
+}
+
+fn is_unique_violation(e: &sqlx::Error, constraint: &str) -> bool {
+    match e {
+        sqlx::Error::Database(e) => e.constraint() == Some(constraint),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn post(status: PostStatus, published_at: Option<DateTime<Utc>>) -> Post {
+        Post {
+            id: Uuid::nil(),
+            title: "Title".to_string(),
+            slug: Some("title".to_string()),
+            content: String::new(),
+            content_format: ContentFormat::Markdown.as_str().to_string(),
+            content_html: None,
+            comments_locked: false,
+            photo: String::new(),
+            user_id: Uuid::nil(),
+            status: status.as_str().to_string(),
+            published_at,
+            language: "english".to_string(),
+            created_at: Some(Utc::now()),
+            updated_at: None,
+            deleted_at: None,
+        }
+    }
+
+    fn days(days: i64) -> DateTime<Utc> {
+        Utc::now() + chrono::Duration::days(days)
+    }
+
+    #[test]
+    fn new_posts_are_drafts_unless_told_otherwise() {
+        assert_eq!(publication(None, None, None), Ok((PostStatus::Draft, None)));
+        assert_eq!(
+            publication(Some("draft"), Some(days(-1)), None),
+            Ok((PostStatus::Draft, None))
+        );
+        assert_eq!(publication(Some("live"), None, None), Err("Invalid status"));
+    }
+
+    #[test]
+    fn publishing_with_a_past_date_keeps_it() {
+        let at = days(-3);
+        assert_eq!(
+            publication(Some("published"), Some(at), None),
+            Ok((PostStatus::Published, Some(at)))
+        );
+
+        let before = Utc::now();
+        let (status, published_at) = publication(Some("published"), None, None).unwrap();
+        assert_eq!(status, PostStatus::Published);
+        assert!(published_at.is_some_and(|at| at >= before && at <= Utc::now()));
+    }
+
+    #[test]
+    fn future_dates_need_the_scheduled_status() {
+        let at = days(2);
+        assert_eq!(
+            publication(Some("scheduled"), Some(at), None),
+            Ok((PostStatus::Scheduled, Some(at)))
+        );
+        assert!(publication(Some("published"), Some(at), None).is_err());
+        assert!(publication(Some("scheduled"), Some(days(-2)), None).is_err());
+        assert!(publication(Some("scheduled"), None, None).is_err());
+    }
+
+    #[test]
+    fn unpublishing_drops_the_publication_date() {
+        let existing = post(PostStatus::Published, Some(days(-3)));
+        assert_eq!(
+            publication(Some("draft"), None, Some(&existing)),
+            Ok((PostStatus::Draft, None))
+        );
+    }
+
+    #[test]
+    fn edits_keep_the_existing_publication_date() {
+        let at = days(-3);
+        let existing = post(PostStatus::Published, Some(at));
+        assert_eq!(
+            publication(None, None, Some(&existing)),
+            Ok((PostStatus::Published, Some(at)))
+        );
+        assert_eq!(
+            publication(Some("published"), None, Some(&existing)),
+            Ok((PostStatus::Published, Some(at)))
+        );
+        assert_eq!(
+            publication(Some("archived"), None, Some(&existing)),
+            Ok((PostStatus::Archived, Some(at)))
+        );
+
+        let at = days(2);
+        let scheduled = post(PostStatus::Scheduled, Some(at));
+        assert_eq!(
+            publication(None, None, Some(&scheduled)),
+            Ok((PostStatus::Scheduled, Some(at)))
+        );
+    }
+
+    #[test]
+    fn publishing_a_scheduled_post_early_dates_it_now() {
+        let scheduled = post(PostStatus::Scheduled, Some(days(2)));
+        let (status, published_at) =
+            publication(Some("published"), None, Some(&scheduled)).unwrap();
+        assert_eq!(status, PostStatus::Published);
+        assert!(published_at.is_some_and(|at| at <= Utc::now()));
+    }
 }
@@ -0,0 +1,67 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Json,
+};
+use serde_json::json;
+
+use crate::{
+    handler::post::find_published_post,
+    model::reaction::{PostReaction, ReactionCount, ReactionKind, ReactionPathSchema},
+    rbac::{require, Authorized},
+    AppState,
+};
+
+/// Adds the user's reaction to a post, or takes it back if it was already
+/// there.
+pub async fn toggle_reaction_handler(
+    Authorized(user, _): Authorized<require::React>,
+    State(data): State<Arc<AppState>>,
+    Path(params): Path<ReactionPathSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let reaction = ReactionKind::parse(&params.reaction).ok_or_else(|| {
+        let kinds: Vec<&str> = ReactionKind::ALL.iter().map(|r| r.as_str()).collect();
+        (
+            StatusCode::BAD_REQUEST,
+            Json(json!({
+                "message": format!("Reaction must be one of: {}", kinds.join(", "))
+            })),
+        )
+    })?;
+    let post = find_published_post(params.id, &data.db).await?;
+    let error = |e| {
+        eprintln!("Error toggling reaction: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error toggling reaction" })),
+        )
+    };
+
+    let reacted = PostReaction {
+        post_id: post.id,
+        user_id: user.id,
+        reaction,
+    }
+    .toggle(&data.db)
+    .await
+    .map_err(error)?;
+
+    let reactions: BTreeMap<String, i32> = ReactionCount::find_for_posts(&[post.id], &data.db)
+        .await
+        .map_err(error)?
+        .into_iter()
+        .map(|count| (count.reaction, count.count))
+        .collect();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "reaction": reaction.as_str(),
+            "reacted": reacted,
+            "reactions": reactions,
+        })),
+    ))
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+    Extension, Json,
+};
+use serde_json::json;
+use similar::TextDiff;
+use uuid::Uuid;
+
+use crate::{
+    handler::post::{find_editable_post, post_responses, save_post},
+    model::{
+        post::Post,
+        post_revision::{PostRevision, RevisionDiffQuery, RevisionPathSchema},
+        taxonomy::PostTaxonomy,
+        user::User,
+    },
+    slug::slugify,
+    AppState,
+};
+
+pub async fn list_revisions_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    find_editable_post(id, &user, &data.db).await?;
+
+    match PostRevision::find_all(id, &data.db).await {
+        Ok(revisions) => Ok((StatusCode::OK, Json(revisions))),
+        Err(e) => {
+            eprintln!("Error getting revisions: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting revisions" })),
+            ))
+        }
+    }
+}
+
+pub async fn diff_revisions_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<RevisionDiffQuery>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    find_editable_post(id, &user, &data.db).await?;
+
+    let from = fetch_revision(id, query.from, &data.db).await?;
+    let to = fetch_revision(id, query.to, &data.db).await?;
+
+    let (old, new) = (from.document(), to.document());
+    let diff = TextDiff::from_lines(&old, &new)
+        .unified_diff()
+        .context_radius(3)
+        .header(
+            &format!("revision {}", from.revision),
+            &format!("revision {}", to.revision),
+        )
+        .to_string();
+
+    Ok((
+        StatusCode::OK,
+        Json(json!({
+            "from": from.revision,
+            "to": to.revision,
+            "diff": diff,
+        })),
+    ))
+}
+
+pub async fn restore_revision_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Path(params): Path<RevisionPathSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let existing = find_editable_post(params.id, &user, &data.db).await?;
+    let revision = fetch_revision(params.id, params.revision, &data.db).await?;
+
+    let base_slug = if revision.title != existing.title {
+        Some(slugify(&revision.title))
+    } else {
+        None
+    };
+
+    // the restored content becomes the newest revision, nothing is overwritten
+    let post = Post {
+        title: revision.title,
+        content: revision.content,
+        content_format: revision.content_format,
+        photo: revision.photo,
+        slug: None,
+        ..existing
+    };
+
+    match save_post(post, base_slug, &PostTaxonomy::default(), user.id, &data.db).await {
+        Ok(post) => {
+            let post = post_responses(vec![post], Some(&user), &data.db)
+                .await?
+                .remove(0);
+            Ok((StatusCode::OK, Json(post)))
+        }
+        Err(e) => {
+            eprintln!("Error restoring revision: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error restoring revision" })),
+            ))
+        }
+    }
+}
+
+async fn fetch_revision(
+    post_id: Uuid,
+    revision: i32,
+    db: &sqlx::PgPool,
+) -> Result<PostRevision, (StatusCode, Json<serde_json::Value>)> {
+    match PostRevision::get(post_id, revision, db).await {
+        Ok(revision) => Ok(revision),
+        Err(sqlx::Error::RowNotFound) => Err((
+            StatusCode::NOT_FOUND,
+            Json(json!({ "message": format!("Revision {} not found", revision) })),
+        )),
+        Err(e) => {
+            eprintln!("Error getting revision: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting revision" })),
+            ))
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use std::sync::Arc;
+
+use axum::{extract::State, http::StatusCode, response::IntoResponse, Json};
+use serde_json::json;
+
+use crate::{
+    model::taxonomy::{same_name, Category, CreateCategorySchema, Tag},
+    rbac::{require, Authorized},
+    slug::slugify_name,
+    AppState,
+};
+
+pub async fn list_tags_handler(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match Tag::find_all_with_counts(&data.db).await {
+        Ok(tags) => Ok((StatusCode::OK, Json(tags))),
+        Err(e) => {
+            eprintln!("Error getting tags: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting tags" })),
+            ))
+        }
+    }
+}
+
+pub async fn list_categories_handler(
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    match Category::find_all_with_counts(&data.db).await {
+        Ok(categories) => Ok((StatusCode::OK, Json(categories))),
+        Err(e) => {
+            eprintln!("Error getting categories: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error getting categories" })),
+            ))
+        }
+    }
+}
+
+pub async fn create_category_handler(
+    Authorized(_, _): Authorized<require::ManageCategories>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<CreateCategorySchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let name = body.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Name must be between 1 and 100 characters" })),
+        ));
+    }
+
+    let slug = slugify_name(body.slug.as_deref().unwrap_or(name));
+    if slug.is_empty() {
+        return Err((
+            StatusCode::BAD_REQUEST,
+            Json(json!({ "message": "Slug needs at least one letter or digit" })),
+        ));
+    }
+    let error = |e| {
+        eprintln!("Error getting categories: {:?}", e);
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(json!({ "message": "Error creating category" })),
+        )
+    };
+
+    // a taken slug is only a duplicate when the names match, otherwise two
+    // different categories would be merged
+    let existing = Category::find_by_slugs(&[slug.to_owned()], &data.db)
+        .await
+        .map_err(error)?;
+    if let Some(category) = existing.first() {
+        return Err(if same_name(&category.name, name) {
+            (
+                StatusCode::CONFLICT,
+                Json(json!({ "message": format!("Category {} already exists", slug) })),
+            )
+        } else {
+            (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "message": format!(
+                        "Category {} would have the same slug {} as the category {}",
+                        name, slug, category.name
+                    )
+                })),
+            )
+        });
+    }
+
+    let parent_id = match &body.parent {
+        Some(parent) => match Category::find_by_slugs(&[parent.to_owned()], &data.db)
+            .await
+            .map_err(error)?
+            .first()
+        {
+            Some(category) => Some(category.id),
+            None => {
+                return Err((
+                    StatusCode::BAD_REQUEST,
+                    Json(json!({ "message": format!("Unknown category: {}", parent) })),
+                ));
+            }
+        },
+        None => None,
+    };
+
+    match Category::insert(name, &slug, parent_id, &data.db).await {
+        Ok(category) => Ok((StatusCode::CREATED, Json(category))),
+        Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Err((
+            StatusCode::CONFLICT,
+            Json(json!({ "message": format!("Category {} already exists", slug) })),
+        )),
+        Err(e) => {
+            eprintln!("Error creating category: {:?}", e);
+            Err((
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(json!({ "message": "Error creating category" })),
+            ))
+        }
+    }
+}
@@ -7,14 +7,22 @@ use axum::{
     response::IntoResponse,
     Extension, Json,
 };
-use axum_extra::extract::cookie::{Cookie, SameSite};
-use jsonwebtoken::{encode, EncodingKey, Header};
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
+use chrono::TimeZone;
 use rand_core::OsRng;
 use serde_json::json;
 
 use crate::{
-    model::user::{LoginUserSchema, RegisterUserSchema, TokenClaims, User},
+    mailer::Email,
+    model::refresh_token::RefreshToken,
+    model::user::{
+        DeleteUserSchema, ForgotPasswordSchema, LoginUserSchema, RefreshTokenSchema,
+        RegisterUserSchema, ResetPasswordSchema, TokenClaims, UpdatePasswordSchema,
+        UpdateUserSchema, User, VerifyEmailSchema,
+    },
+    model::user_token::{UserToken, RESET_PASSWORD, VERIFY_EMAIL},
     response::FilteredUser,
+    token::{generate_token, hash_token},
     AppState,
 };
 
@@ -29,10 +37,16 @@ pub async fn health_checker_handler() -> impl IntoResponse {
     Json(json_response)
 }
 
+pub async fn jwks_handler(State(data): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(data.keys.jwks().clone())
+}
+
 pub async fn register_user_handler(
     State(data): State<Arc<AppState>>,
     Json(body): Json<RegisterUserSchema>,
 ) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    validate_password(&body.password)?;
+
     let user_exists: Option<bool> =
         sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
             .bind(body.email.to_owned().to_ascii_lowercase())
@@ -56,17 +70,7 @@ pub async fn register_user_handler(
         }
     }
 
-    let salt = SaltString::generate(&mut OsRng);
-    let hashed_password = Argon2::default()
-        .hash_password(body.password.as_bytes(), &salt)
-        .map_err(|e| {
-            let error_response = serde_json::json!({
-                "status": "fail",
-                "message": format!("Error while hashing password: {}", e),
-            });
-            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
-        })
-        .map(|hash| hash.to_string())?;
+    let hashed_password = hash_password(&body.password)?;
 
     let user = sqlx::query_as!(
         User,
@@ -85,6 +89,54 @@ pub async fn register_user_handler(
         (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
     })?;
 
+    // the account is created either way, the user can ask for a new email later
+    if let Err(e) = send_verification_email(&user, &data).await {
+        eprintln!("Error sending verification email: {}", e);
+    }
+
+    let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "user": filter_user_record(&user)
+    })});
+
+    Ok(Json(user_response))
+}
+
+pub async fn verify_email_handler(
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<VerifyEmailSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let invalid_token = || {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Invalid or expired verification token",
+        });
+        (StatusCode::BAD_REQUEST, Json(error_response))
+    };
+
+    let user = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE email = $1",
+        body.email.to_ascii_lowercase()
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(database_error)?
+    .ok_or_else(invalid_token)?;
+
+    UserToken::consume(user.id, VERIFY_EMAIL, &hash_token(&body.token), &data.db)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(invalid_token)?;
+
+    let user = sqlx::query_as!(
+        User,
+        "UPDATE users SET verified = TRUE, updated_at = NOW() WHERE id = $1 RETURNING *",
+        user.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(database_error)?;
+
     let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
         "user": filter_user_record(&user)
     })});
@@ -92,6 +144,111 @@ pub async fn register_user_handler(
     Ok(Json(user_response))
 }
 
+pub async fn forgot_password_handler(
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<ForgotPasswordSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // do the lookup and send the email in the background so neither the response
+    // nor its timing reveals whether the account exists
+    tokio::spawn(async move {
+        let user = sqlx::query_as!(
+            User,
+            "SELECT * FROM users WHERE email = $1",
+            body.email.to_ascii_lowercase()
+        )
+        .fetch_optional(&data.db)
+        .await;
+
+        match user {
+            Ok(Some(user)) => {
+                if let Err(e) = send_password_reset_email(&user, &data).await {
+                    eprintln!("Error sending password reset email: {}", e);
+                }
+            }
+            Ok(None) => {}
+            Err(e) => eprintln!("Error getting user: {:?}", e),
+        }
+    });
+
+    Ok(Json(json!({
+        "status": "success",
+        "message": "If an account with that email exists, a password reset link has been sent",
+    })))
+}
+
+pub async fn reset_password_handler(
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<ResetPasswordSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // checked first so a rejected password doesn't use up the token
+    validate_password(&body.password)?;
+
+    // unknown emails get the same answer as bad tokens
+    let invalid_token = || {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Invalid or expired reset token",
+        });
+        (StatusCode::BAD_REQUEST, Json(error_response))
+    };
+
+    let user = sqlx::query_as!(
+        User,
+        "SELECT * FROM users WHERE email = $1",
+        body.email.to_ascii_lowercase()
+    )
+    .fetch_optional(&data.db)
+    .await
+    .map_err(database_error)?
+    .ok_or_else(invalid_token)?;
+
+    UserToken::consume(user.id, RESET_PASSWORD, &hash_token(&body.token), &data.db)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(invalid_token)?;
+
+    let hashed_password = hash_password(&body.password)?;
+
+    sqlx::query!(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+        hashed_password,
+        user.id
+    )
+    .execute(&data.db)
+    .await
+    .map_err(database_error)?;
+
+    revoke_sessions(user.id, &data).await?;
+
+    Ok(Json(json!({
+        "status": "success",
+        "message": "Password has been reset, please log in again",
+    })))
+}
+
+pub async fn resend_verification_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if user.verified {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Email is already verified",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    send_verification_email(&user, &data).await.map_err(|e| {
+        let error_response = serde_json::json!({
+            "status": "error",
+            "message": format!("Error sending verification email: {}", e),
+        });
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
+    })?;
+
+    Ok(Json(json!({"status": "success"})))
+}
+
 pub async fn login_user_handler(
     State(data): State<Arc<AppState>>,
     Json(body): Json<LoginUserSchema>,
@@ -118,14 +275,7 @@ pub async fn login_user_handler(
         (StatusCode::BAD_REQUEST, Json(error_response))
     })?;
 
-    let is_valid = match PasswordHash::new(&user.password) {
-        Ok(parsed_hash) => Argon2::default()
-            .verify_password(body.password.as_bytes(), &parsed_hash)
-            .map_or(false, |_| true),
-        Err(_) => false,
-    };
-
-    if !is_valid {
+    if !verify_password(&body.password, &user.password) {
         let error_response = serde_json::json!({
             "status": "fail",
             "message": "Invalid email or password"
@@ -133,63 +283,498 @@ pub async fn login_user_handler(
         return Err((StatusCode::BAD_REQUEST, Json(error_response)));
     }
 
+    if user.suspended_at.is_some() {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Your account has been suspended",
+        });
+        return Err((StatusCode::FORBIDDEN, Json(error_response)));
+    }
+
+    // every login starts a new refresh token family
+    let (response, _) = issue_tokens(&user, uuid::Uuid::new_v4(), &data).await?;
+    Ok(response)
+}
+
+pub async fn refresh_token_handler(
+    cookie_jar: CookieJar,
+    State(data): State<Arc<AppState>>,
+    body: Option<Json<RefreshTokenSchema>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let refresh_token = body
+        .map(|Json(body)| body.refresh_token)
+        .or_else(|| {
+            cookie_jar
+                .get("refresh_token")
+                .map(|cookie| cookie.value().to_string())
+        })
+        .ok_or_else(|| {
+            let error_response = serde_json::json!({
+                "status": "fail",
+                "message": "Please provide a refresh token",
+            });
+            (StatusCode::UNAUTHORIZED, Json(error_response))
+        })?;
+
+    let invalid_token = || {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Invalid or expired refresh token",
+        });
+        (StatusCode::UNAUTHORIZED, Json(error_response))
+    };
+
+    let stored = RefreshToken::find_by_hash(&hash_token(&refresh_token), &data.db)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(invalid_token)?;
+
+    // a token that was already rotated is being replayed: assume it was stolen and
+    // revoke every token descending from the same login
+    if stored.revoked_at.is_some() {
+        RefreshToken::revoke_family(stored.family_id, &data.db)
+            .await
+            .map_err(database_error)?;
+        return Err(invalid_token());
+    }
+
+    if stored.expires_at <= chrono::Utc::now() {
+        return Err(invalid_token());
+    }
+
+    let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", stored.user_id)
+        .fetch_optional(&data.db)
+        .await
+        .map_err(database_error)?
+        .ok_or_else(invalid_token)?;
+
+    // suspended accounts don't get new tokens, and this login is over for good
+    if user.suspended_at.is_some() {
+        RefreshToken::revoke_family(stored.family_id, &data.db)
+            .await
+            .map_err(database_error)?;
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Your account has been suspended",
+        });
+        return Err((StatusCode::FORBIDDEN, Json(error_response)));
+    }
+
+    let (response, new_token) = issue_tokens(&user, stored.family_id, &data).await?;
+
+    let rotated = RefreshToken::rotate(stored.id, new_token.id, &data.db)
+        .await
+        .map_err(database_error)?;
+
+    // lost a race against another request presenting the same token
+    if !rotated {
+        RefreshToken::revoke_family(stored.family_id, &data.db)
+            .await
+            .map_err(database_error)?;
+        return Err(invalid_token());
+    }
+
+    Ok(response)
+}
+
+pub async fn logout_handler(
+    cookie_jar: CookieJar,
+    Extension(user): Extension<User>,
+    Extension(claims): Extension<TokenClaims>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    // the auth middleware already validated the jti
+    let jti = uuid::Uuid::parse_str(&claims.jti).unwrap_or_default();
+    let expires_at = chrono::Utc
+        .timestamp_opt(claims.exp as i64, 0)
+        .single()
+        .unwrap_or_else(chrono::Utc::now);
+    data.revocations
+        .revoke_token(jti, user.id, expires_at, &data.db)
+        .await
+        .map_err(database_error)?;
+
+    if let Some(refresh_token) = cookie_jar.get("refresh_token") {
+        let stored = RefreshToken::find_by_hash(&hash_token(refresh_token.value()), &data.db)
+            .await
+            .map_err(database_error)?;
+
+        if let Some(stored) = stored {
+            RefreshToken::revoke_family(stored.family_id, &data.db)
+                .await
+                .map_err(database_error)?;
+        }
+    }
+
+    Ok(clear_auth_cookies())
+}
+
+pub async fn logout_all_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    revoke_sessions(user.id, &data).await?;
+
+    Ok(clear_auth_cookies())
+}
+
+pub async fn get_me_handler(
+    Extension(user): Extension<User>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    let json_response = serde_json::json!({
+        "status":  "success",
+        "data": serde_json::json!({
+            "user": filter_user_record(&user)
+        })
+    });
+
+    Ok(Json(json_response))
+}
+
+/// Signs a new access token and stores a new refresh token in `family_id`,
+/// returning the response carrying both along with the stored refresh token.
+async fn issue_tokens(
+    user: &User,
+    family_id: uuid::Uuid,
+    data: &AppState,
+) -> Result<(Response<String>, RefreshToken), (StatusCode, Json<serde_json::Value>)> {
     let now = chrono::Utc::now();
     let iat = now.timestamp() as usize;
-    let exp = (now + chrono::Duration::minutes(60)).timestamp() as usize;
+    let exp = (now + data.env.jwt_expires_in).timestamp() as usize;
     let claims: TokenClaims = TokenClaims {
         sub: user.id.to_string(),
         exp,
         iat,
+        jti: uuid::Uuid::new_v4().to_string(),
+        iss: data.env.jwt_issuer.to_owned(),
+        aud: data.env.jwt_audience.to_owned(),
+        iat_ms: Some(now.timestamp_millis()),
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(data.env.jwt_secret.as_ref()),
+    let token = data.keys.encode(&claims).map_err(|e| {
+        let error_response = serde_json::json!({
+            "status": "error",
+            "message": format!("Error signing token: {}", e),
+        });
+        (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
+    })?;
+
+    let refresh_token = generate_token();
+    let refresh_expires_at = now + data.env.refresh_token_maxage;
+    let stored = RefreshToken::insert(
+        user.id,
+        family_id,
+        &hash_token(&refresh_token),
+        refresh_expires_at,
+        &data.db,
     )
-    .unwrap();
+    .await
+    .map_err(database_error)?;
 
     let cookie = Cookie::build(("token", token.to_owned()))
         .path("/")
-        .max_age(time::Duration::hours(1))
+        .max_age(time::Duration::seconds(data.env.jwt_maxage.num_seconds()))
         .same_site(SameSite::Lax)
         .http_only(true);
 
-    let mut response = Response::new(json!({"status": "success", "token": token}).to_string());
+    let refresh_cookie = Cookie::build(("refresh_token", refresh_token.to_owned()))
+        .path("/api/auth")
+        .max_age(time::Duration::seconds(
+            data.env.refresh_token_maxage.num_seconds(),
+        ))
+        .same_site(SameSite::Lax)
+        .http_only(true);
+
+    let mut response = Response::new(
+        json!({"status": "success", "token": token, "refresh_token": refresh_token}).to_string(),
+    );
     response
         .headers_mut()
-        .insert(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+        .append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    response
+        .headers_mut()
+        .append(header::SET_COOKIE, refresh_cookie.to_string().parse().unwrap());
+    Ok((response, stored))
+}
+
+pub async fn update_me_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<UpdateUserSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if [&body.name, &body.email, &body.photo]
+        .iter()
+        .any(|field| field.as_ref().is_some_and(|value| value.trim().is_empty()))
+    {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Fields must not be empty",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    let email = body
+        .email
+        .map(|email| email.to_ascii_lowercase())
+        .filter(|email| *email != user.email);
+
+    if let Some(email) = &email {
+        let email_taken: bool =
+            sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE email = $1)")
+                .bind(email)
+                .fetch_one(&data.db)
+                .await
+                .map_err(database_error)?;
+
+        if email_taken {
+            let error_response = serde_json::json!({
+                "status": "fail",
+                "message": "User with that email already exists",
+            });
+            return Err((StatusCode::CONFLICT, Json(error_response)));
+        }
+    }
+
+    // a new email address has to be verified again
+    let user = sqlx::query_as!(
+        User,
+        r#"
+        UPDATE users
+        SET name = COALESCE($1, name),
+            email = COALESCE($2, email),
+            photo = COALESCE($3, photo),
+            verified = CASE WHEN $2::VARCHAR IS NULL THEN verified ELSE FALSE END,
+            updated_at = NOW()
+        WHERE id = $4
+        RETURNING *
+        "#,
+        body.name,
+        email,
+        body.photo,
+        user.id
+    )
+    .fetch_one(&data.db)
+    .await
+    .map_err(database_error)?;
+
+    if email.is_some() {
+        if let Err(e) = send_verification_email(&user, &data).await {
+            eprintln!("Error sending verification email: {}", e);
+        }
+    }
+
+    let user_response = serde_json::json!({"status": "success","data": serde_json::json!({
+        "user": filter_user_record(&user)
+    })});
+
+    Ok(Json(user_response))
+}
+
+pub async fn update_password_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<UpdatePasswordSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if !verify_password(&body.password, &user.password) {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Current password is incorrect",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    validate_password(&body.new_password)?;
+
+    let hashed_password = hash_password(&body.new_password)?;
+
+    sqlx::query!(
+        "UPDATE users SET password = $1, updated_at = NOW() WHERE id = $2",
+        hashed_password,
+        user.id
+    )
+    .execute(&data.db)
+    .await
+    .map_err(database_error)?;
+
+    // sign out every other session and hand this one a fresh pair of tokens
+    revoke_sessions(user.id, &data).await?;
+    let (response, _) = issue_tokens(&user, uuid::Uuid::new_v4(), &data).await?;
     Ok(response)
 }
 
-pub async fn logout_handler() -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+pub async fn delete_me_handler(
+    Extension(user): Extension<User>,
+    State(data): State<Arc<AppState>>,
+    Json(body): Json<DeleteUserSchema>,
+) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
+    if body.email.to_ascii_lowercase() != user.email
+        || !verify_password(&body.password, &user.password)
+    {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Invalid email or password",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    // posts and tokens go with the user through ON DELETE CASCADE
+    sqlx::query!("DELETE FROM users WHERE id = $1", user.id)
+        .execute(&data.db)
+        .await
+        .map_err(database_error)?;
+
+    Ok(clear_auth_cookies())
+}
+
+async fn send_verification_email(
+    user: &User,
+    data: &AppState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let token = generate_token();
+    let expires_at = chrono::Utc::now() + data.env.verification_token_maxage;
+    UserToken::insert(user.id, VERIFY_EMAIL, &hash_token(&token), expires_at, &data.db).await?;
+
+    let link = format!(
+        "{}/verify-email?email={}&token={}",
+        data.env.app_url,
+        urlencoding::encode(&user.email),
+        token
+    );
+
+    data.mailer
+        .send(Email {
+            to: user.email.to_owned(),
+            subject: "Verify your email address".to_string(),
+            body: format!(
+                "Hi {},\n\nPlease confirm your email address by opening the link below:\n\n{}\n",
+                user.name, link
+            ),
+        })
+        .await?;
+
+    Ok(())
+}
+
+pub async fn send_password_reset_email(
+    user: &User,
+    data: &AppState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let token = generate_token();
+    let expires_at = chrono::Utc::now() + data.env.password_reset_token_maxage;
+    UserToken::insert(
+        user.id,
+        RESET_PASSWORD,
+        &hash_token(&token),
+        expires_at,
+        &data.db,
+    )
+    .await?;
+
+    let link = format!(
+        "{}/reset-password?email={}&token={}",
+        data.env.app_url,
+        urlencoding::encode(&user.email),
+        token
+    );
+
+    data.mailer
+        .send(Email {
+            to: user.email.to_owned(),
+            subject: "Reset your password".to_string(),
+            body: format!(
+                "Hi {},\n\nYou can choose a new password by opening the link below:\n\n{}\n\nIf you didn't ask for this, you can ignore this email.\n",
+                user.name, link
+            ),
+        })
+        .await?;
+
+    Ok(())
+}
+
+fn verify_password(password: &str, hashed_password: &str) -> bool {
+    match PasswordHash::new(hashed_password) {
+        Ok(parsed_hash) => Argon2::default()
+            .verify_password(password.as_bytes(), &parsed_hash)
+            .is_ok(),
+        Err(_) => false,
+    }
+}
+
+fn validate_password(password: &str) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    if password.trim().is_empty() {
+        let error_response = serde_json::json!({
+            "status": "fail",
+            "message": "Password must not be empty",
+        });
+        return Err((StatusCode::BAD_REQUEST, Json(error_response)));
+    }
+
+    Ok(())
+}
+
+pub fn hash_password(password: &str) -> Result<String, (StatusCode, Json<serde_json::Value>)> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map_err(|e| {
+            let error_response = serde_json::json!({
+                "status": "fail",
+                "message": format!("Error while hashing password: {}", e),
+            });
+            (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
+        })
+        .map(|hash| hash.to_string())
+}
+
+/// Revokes every access and refresh token issued to the user.
+pub async fn revoke_sessions(
+    user_id: uuid::Uuid,
+    data: &AppState,
+) -> Result<(), (StatusCode, Json<serde_json::Value>)> {
+    data.revocations
+        .revoke_user(user_id, &data.db)
+        .await
+        .map_err(database_error)?;
+
+    RefreshToken::revoke_all_for_user(user_id, &data.db)
+        .await
+        .map_err(database_error)?;
+
+    Ok(())
+}
+
+fn clear_auth_cookies() -> Response<String> {
     let cookie = Cookie::build(("token", ""))
         .path("/")
         .max_age(time::Duration::hours(-1))
         .same_site(SameSite::Lax)
         .http_only(true);
 
+    let refresh_cookie = Cookie::build(("refresh_token", ""))
+        .path("/api/auth")
+        .max_age(time::Duration::hours(-1))
+        .same_site(SameSite::Lax)
+        .http_only(true);
+
     let mut response = Response::new(json!({"status": "success"}).to_string());
     response
         .headers_mut()
-        .insert(header::SET_COOKIE, cookie.to_string().parse().unwrap());
-    Ok(response)
+        .append(header::SET_COOKIE, cookie.to_string().parse().unwrap());
+    response
+        .headers_mut()
+        .append(header::SET_COOKIE, refresh_cookie.to_string().parse().unwrap());
+    response
 }
 
-pub async fn get_me_handler(
-    Extension(user): Extension<User>,
-) -> Result<impl IntoResponse, (StatusCode, Json<serde_json::Value>)> {
-    let json_response = serde_json::json!({
-        "status":  "success",
-        "data": serde_json::json!({
-            "user": filter_user_record(&user)
-        })
+pub fn database_error(e: sqlx::Error) -> (StatusCode, Json<serde_json::Value>) {
+    let error_response = serde_json::json!({
+        "status": "error",
+        "message": format!("Database error: {}", e),
     });
-
-    Ok(Json(json_response))
+    (StatusCode::INTERNAL_SERVER_ERROR, Json(error_response))
 }
 
-fn filter_user_record(user: &User) -> FilteredUser {
+pub fn filter_user_record(user: &User) -> FilteredUser {
     FilteredUser {
         id: user.id.to_string(),
         email: user.email.to_owned(),
@@ -199,5 +784,7 @@ fn filter_user_record(user: &User) -> FilteredUser {
         verified: user.verified,
         created_at: user.created_at.unwrap(),
         updated_at: user.updated_at.unwrap(),
+        suspended_at: user.suspended_at,
+        suspension_reason: user.suspension_reason.to_owned(),
     }
 }
@@ -9,7 +9,6 @@ use axum::{
 };
 
 use axum_extra::extract::cookie::CookieJar;
-use jsonwebtoken::{decode, DecodingKey, Validation};
 use serde::Serialize;
 
 use crate::{
@@ -29,7 +28,43 @@ pub async fn auth(
     mut req: Request<Body>,
     next: Next,
 ) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
-    let token = cookie_jar
+    let token = request_token(&cookie_jar, &req).ok_or_else(|| {
+        let json_error = ErrorResponse {
+            status: "fail",
+            message: "You are not logged in, please provide token".to_string(),
+        };
+        (StatusCode::UNAUTHORIZED, Json(json_error))
+    })?;
+
+    let (user, claims) = authenticate(&token, &data).await?;
+
+    req.extensions_mut().insert(user);
+    req.extensions_mut().insert(claims);
+    Ok(next.run(req).await)
+}
+
+/// Like `auth`, but lets requests without a token through anonymously. A token
+/// that is present still has to be valid, handlers read the user with
+/// `Option<Extension<User>>`.
+pub async fn optional_auth(
+    cookie_jar: CookieJar,
+    State(data): State<Arc<AppState>>,
+    mut req: Request<Body>,
+    next: Next,
+) -> Result<impl IntoResponse, (StatusCode, Json<ErrorResponse>)> {
+    if let Some(token) = request_token(&cookie_jar, &req) {
+        let (user, claims) = authenticate(&token, &data).await?;
+
+        req.extensions_mut().insert(user);
+        req.extensions_mut().insert(claims);
+    }
+
+    Ok(next.run(req).await)
+}
+
+/// The token from the `token` cookie or the `Authorization: Bearer` header.
+fn request_token(cookie_jar: &CookieJar, req: &Request<Body>) -> Option<String> {
+    cookie_jar
         .get("token")
         .map(|cookie| cookie.value().to_string())
         .or_else(|| {
@@ -43,37 +78,48 @@ pub async fn auth(
                         None
                     }
                 })
-        });
+        })
+}
+
+async fn authenticate(
+    token: &str,
+    data: &AppState,
+) -> Result<(User, TokenClaims), (StatusCode, Json<ErrorResponse>)> {
+    let claims = data
+        .keys
+        .decode::<TokenClaims>(token)
+        .map_err(|_| {
+            let json_error = ErrorResponse {
+                status: "fail",
+                message: "Invalid token".to_string(),
+            };
+            (StatusCode::UNAUTHORIZED, Json(json_error))
+        })?
+        .claims;
 
-    let token = token.ok_or_else(|| {
+    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| {
         let json_error = ErrorResponse {
             status: "fail",
-            message: "You are not logged in, please provide token".to_string(),
+            message: "Invalid token".to_string(),
         };
         (StatusCode::UNAUTHORIZED, Json(json_error))
     })?;
 
-    let claims = decode::<TokenClaims>(
-        &token,
-        &DecodingKey::from_secret(data.env.jwt_secret.as_ref()),
-        &Validation::default(),
-    )
-    .map_err(|_| {
+    let jti = uuid::Uuid::parse_str(&claims.jti).map_err(|_| {
         let json_error = ErrorResponse {
             status: "fail",
             message: "Invalid token".to_string(),
         };
         (StatusCode::UNAUTHORIZED, Json(json_error))
-    })?
-    .claims;
+    })?;
 
-    let user_id = uuid::Uuid::parse_str(&claims.sub).map_err(|_| {
+    if data.revocations.is_revoked(jti, user_id, claims.issued_at_ms()) {
         let json_error = ErrorResponse {
             status: "fail",
-            message: "Invalid token".to_string(),
+            message: "Token has been revoked, please log in again".to_string(),
         };
-        (StatusCode::UNAUTHORIZED, Json(json_error))
-    })?;
+        return Err((StatusCode::UNAUTHORIZED, Json(json_error)));
+    }
 
     let user = sqlx::query_as!(User, "SELECT * FROM users WHERE id = $1", user_id)
         .fetch_optional(&data.db)
@@ -94,6 +140,13 @@ pub async fn auth(
         (StatusCode::UNAUTHORIZED, Json(json_error))
     })?;
 
-    req.extensions_mut().insert(user);
-    Ok(next.run(req).await)
+    if user.suspended_at.is_some() {
+        let json_error = ErrorResponse {
+            status: "fail",
+            message: "Your account has been suspended".to_string(),
+        };
+        return Err((StatusCode::FORBIDDEN, Json(json_error)));
+    }
+
+    Ok((user, claims))
 }
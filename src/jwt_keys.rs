@@ -0,0 +1,433 @@
+use std::{collections::HashMap, fmt};
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header, encode,
+    errors::ErrorKind,
+    jwk::{
+        AlgorithmParameters, CommonParameters, EllipticCurve, EllipticCurveKeyParameters,
+        EllipticCurveKeyType, Jwk, JwkSet, KeyAlgorithm, OctetKeyPairParameters, OctetKeyPairType,
+        PublicKeyUse, RSAKeyParameters, RSAKeyType,
+    },
+    Algorithm, DecodingKey, EncodingKey, Header, TokenData, Validation,
+};
+use serde::{de::DeserializeOwned, Serialize};
+use simple_asn1::ASN1Block;
+
+use crate::config::Config;
+
+#[derive(Debug)]
+pub struct KeyError(pub String);
+
+impl fmt::Display for KeyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JWT key error: {}", self.0)
+    }
+}
+
+impl std::error::Error for KeyError {}
+
+/// Keys used to sign and verify access tokens.
+///
+/// With HS256 the shared `JWT_SECRET` is used for both. With RS256, ES256 or
+/// EdDSA tokens are signed with the private key `JWT_PRIVATE_KEY_PATH` under
+/// the `kid` `JWT_KEY_ID`, and verified with any of the public keys listed in
+/// `JWT_PUBLIC_KEYS` (`kid=path,kid=path`), so old keys keep working while a
+/// new one is rolled out. The public keys are published as a JWK set.
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    kid: Option<String>,
+    encoding_key: EncodingKey,
+    decoding_keys: HashMap<String, DecodingKey>,
+    // used for tokens without a `kid`, only set for HS256
+    default_decoding_key: Option<DecodingKey>,
+    jwks: JwkSet,
+    validation: Validation,
+}
+
+impl JwtKeys {
+    pub fn from_config(config: &Config) -> Result<JwtKeys, KeyError> {
+        let algorithm = match config.jwt_algorithm.as_str() {
+            "HS256" => Algorithm::HS256,
+            "RS256" => Algorithm::RS256,
+            "ES256" => Algorithm::ES256,
+            "EdDSA" => Algorithm::EdDSA,
+            other => return Err(KeyError(format!("Unsupported JWT_ALGORITHM: {}", other))),
+        };
+
+        let validation = validation(
+            algorithm,
+            config.jwt_issuer.as_deref(),
+            config.jwt_audience.as_deref(),
+        );
+
+        if algorithm == Algorithm::HS256 {
+            let secret = config.jwt_secret.as_bytes();
+            let mut decoding_keys = HashMap::new();
+            if let Some(kid) = &config.jwt_key_id {
+                decoding_keys.insert(kid.to_owned(), DecodingKey::from_secret(secret));
+            }
+
+            return Ok(JwtKeys {
+                algorithm,
+                kid: config.jwt_key_id.clone(),
+                encoding_key: EncodingKey::from_secret(secret),
+                decoding_keys,
+                default_decoding_key: Some(DecodingKey::from_secret(secret)),
+                // shared secrets are never published
+                jwks: JwkSet { keys: vec![] },
+                validation,
+            });
+        }
+
+        let kid = config
+            .jwt_key_id
+            .clone()
+            .ok_or_else(|| KeyError("JWT_KEY_ID must be set".to_string()))?;
+        let private_key_path = config
+            .jwt_private_key_path
+            .as_ref()
+            .ok_or_else(|| KeyError("JWT_PRIVATE_KEY_PATH must be set".to_string()))?;
+
+        let private_pem = read_pem(private_key_path)?;
+        let encoding_key = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(&private_pem),
+            Algorithm::ES256 => EncodingKey::from_ec_pem(&private_pem),
+            _ => EncodingKey::from_ed_pem(&private_pem),
+        }
+        .map_err(|e| KeyError(format!("Invalid private key {}: {}", private_key_path, e)))?;
+
+        let mut decoding_keys = HashMap::new();
+        let mut jwks = JwkSet { keys: vec![] };
+        for (public_kid, path) in &config.jwt_public_keys {
+            let public_pem = read_pem(path)?;
+            let decoding_key = match algorithm {
+                Algorithm::RS256 => DecodingKey::from_rsa_pem(&public_pem),
+                Algorithm::ES256 => DecodingKey::from_ec_pem(&public_pem),
+                _ => DecodingKey::from_ed_pem(&public_pem),
+            }
+            .map_err(|e| KeyError(format!("Invalid public key {}: {}", path, e)))?;
+
+            decoding_keys.insert(public_kid.to_owned(), decoding_key);
+            jwks.keys.push(
+                public_jwk(algorithm, public_kid, &public_pem)
+                    .map_err(|e| KeyError(format!("Invalid public key {}: {}", path, e.0)))?,
+            );
+        }
+
+        if !decoding_keys.contains_key(&kid) {
+            return Err(KeyError(format!(
+                "JWT_PUBLIC_KEYS must contain the public key for JWT_KEY_ID {}",
+                kid
+            )));
+        }
+
+        Ok(JwtKeys {
+            algorithm,
+            kid: Some(kid),
+            encoding_key,
+            decoding_keys,
+            default_decoding_key: None,
+            jwks,
+            validation,
+        })
+    }
+
+    pub fn encode<T: Serialize>(&self, claims: &T) -> jsonwebtoken::errors::Result<String> {
+        let mut header = Header::new(self.algorithm);
+        header.kid = self.kid.clone();
+        encode(&header, claims, &self.encoding_key)
+    }
+
+    pub fn decode<T: DeserializeOwned>(
+        &self,
+        token: &str,
+    ) -> jsonwebtoken::errors::Result<TokenData<T>> {
+        let header = decode_header(token)?;
+        if header.alg != self.algorithm {
+            return Err(ErrorKind::InvalidAlgorithm.into());
+        }
+
+        let key = match &header.kid {
+            Some(kid) => self.decoding_keys.get(kid),
+            None => self.default_decoding_key.as_ref(),
+        }
+        .ok_or(ErrorKind::InvalidToken)?;
+
+        decode::<T>(token, key, &self.validation)
+    }
+
+    pub fn jwks(&self) -> &JwkSet {
+        &self.jwks
+    }
+}
+
+/// Checks the signature and expiry, and the issuer and audience when they are
+/// configured. Tokens without a configured claim are rejected, jsonwebtoken
+/// only checks the claims a token has.
+fn validation(algorithm: Algorithm, issuer: Option<&str>, audience: Option<&str>) -> Validation {
+    let mut validation = Validation::new(algorithm);
+    let mut required = vec!["exp"];
+    if let Some(issuer) = issuer {
+        validation.set_issuer(&[issuer]);
+        required.push("iss");
+    }
+    if let Some(audience) = audience {
+        validation.set_audience(&[audience]);
+        required.push("aud");
+    }
+    validation.set_required_spec_claims(&required);
+    validation
+}
+
+fn read_pem(path: &str) -> Result<Vec<u8>, KeyError> {
+    std::fs::read(path).map_err(|e| KeyError(format!("Could not read {}: {}", path, e)))
+}
+
+/// Builds the JWK for a PEM encoded public key (SubjectPublicKeyInfo, or
+/// PKCS#1 for RSA).
+fn public_jwk(algorithm: Algorithm, kid: &str, public_pem: &[u8]) -> Result<Jwk, KeyError> {
+    let pem = pem::parse(public_pem).map_err(|e| KeyError(e.to_string()))?;
+
+    let (key_algorithm, parameters) = match algorithm {
+        Algorithm::RS256 => {
+            // PKCS#1 keys are the bare RSAPublicKey sequence
+            let rsa_key = if pem.tag() == "RSA PUBLIC KEY" {
+                pem.contents().to_vec()
+            } else {
+                subject_public_key(pem.contents())?
+            };
+
+            let (n, e) = match parse_der(&rsa_key)?.as_slice() {
+                [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+                    [ASN1Block::Integer(_, n), ASN1Block::Integer(_, e)] => {
+                        (n.to_bytes_be().1, e.to_bytes_be().1)
+                    }
+                    _ => return Err(KeyError("Malformed RSA public key".to_string())),
+                },
+                _ => return Err(KeyError("Malformed RSA public key".to_string())),
+            };
+
+            (
+                KeyAlgorithm::RS256,
+                AlgorithmParameters::RSA(RSAKeyParameters {
+                    key_type: RSAKeyType::RSA,
+                    n: URL_SAFE_NO_PAD.encode(n),
+                    e: URL_SAFE_NO_PAD.encode(e),
+                }),
+            )
+        }
+        Algorithm::ES256 => {
+            // uncompressed point: 0x04 || x || y
+            let point = subject_public_key(pem.contents())?;
+            if point.len() != 65 || point[0] != 0x04 {
+                return Err(KeyError(
+                    "Expected an uncompressed P-256 public key".to_string(),
+                ));
+            }
+
+            (
+                KeyAlgorithm::ES256,
+                AlgorithmParameters::EllipticCurve(EllipticCurveKeyParameters {
+                    key_type: EllipticCurveKeyType::EC,
+                    curve: EllipticCurve::P256,
+                    x: URL_SAFE_NO_PAD.encode(&point[1..33]),
+                    y: URL_SAFE_NO_PAD.encode(&point[33..]),
+                }),
+            )
+        }
+        _ => {
+            let x = subject_public_key(pem.contents())?;
+            if x.len() != 32 {
+                return Err(KeyError("Expected an Ed25519 public key".to_string()));
+            }
+
+            (
+                KeyAlgorithm::EdDSA,
+                AlgorithmParameters::OctetKeyPair(OctetKeyPairParameters {
+                    key_type: OctetKeyPairType::OctetKeyPair,
+                    curve: EllipticCurve::Ed25519,
+                    x: URL_SAFE_NO_PAD.encode(x),
+                }),
+            )
+        }
+    };
+
+    Ok(Jwk {
+        common: CommonParameters {
+            public_key_use: Some(PublicKeyUse::Signature),
+            key_algorithm: Some(key_algorithm),
+            key_id: Some(kid.to_string()),
+            ..Default::default()
+        },
+        algorithm: parameters,
+    })
+}
+
+/// Extracts the key bits from a SubjectPublicKeyInfo structure.
+fn subject_public_key(der: &[u8]) -> Result<Vec<u8>, KeyError> {
+    match parse_der(der)?.as_slice() {
+        [ASN1Block::Sequence(_, fields)] => match fields.as_slice() {
+            [ASN1Block::Sequence(..), ASN1Block::BitString(_, _, bits)] => Ok(bits.to_owned()),
+            _ => Err(KeyError("Malformed SubjectPublicKeyInfo".to_string())),
+        },
+        _ => Err(KeyError("Malformed SubjectPublicKeyInfo".to_string())),
+    }
+}
+
+fn parse_der(der: &[u8]) -> Result<Vec<ASN1Block>, KeyError> {
+    simple_asn1::from_der(der).map_err(|e| KeyError(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // generated with openssl, the expected JWK fields were read off
+    // `openssl rsa -modulus` and `openssl ec -text`
+    const RSA_PUBLIC_KEY: &str = "\
+-----BEGIN PUBLIC KEY-----
+MIIBIjANBgkqhkiG9w0BAQEFAAOCAQ8AMIIBCgKCAQEAsU4ch1gAS+fVaBsJ7Rn5
+cikXEJc/ogumRuDFBvocycbUJRG2b9BIcZLY887YvCfZkG3qkV3R0yVGlfUGhsLN
+5s+IO+r4IbRPUeECcBiNj7WtKBknHVjdH8QxITppU8HLJzCh3xBCCN7JyZ8iiQiO
+RZp5XNQtc9EGSemv5nVGOo+UCwmmX1hW8TOwHOEKl1G2hRHRjqSVBl4e8vumVib4
+PcW2OT/S/FKez+lmlz9u5qHCmkvO4rkcneGfo+mMI+wQK+LpCGQ341+vXyybFlhl
+tFjaNRTInazkX17KU04G39QVbft2HI0Ns0VM/IjomgVMHFg0FztssvWRS/GxceWp
+RwIDAQAB
+-----END PUBLIC KEY-----
+";
+
+    const RSA_PKCS1_PUBLIC_KEY: &str = "\
+-----BEGIN RSA PUBLIC KEY-----
+MIIBCgKCAQEAsU4ch1gAS+fVaBsJ7Rn5cikXEJc/ogumRuDFBvocycbUJRG2b9BI
+cZLY887YvCfZkG3qkV3R0yVGlfUGhsLN5s+IO+r4IbRPUeECcBiNj7WtKBknHVjd
+H8QxITppU8HLJzCh3xBCCN7JyZ8iiQiORZp5XNQtc9EGSemv5nVGOo+UCwmmX1hW
+8TOwHOEKl1G2hRHRjqSVBl4e8vumVib4PcW2OT/S/FKez+lmlz9u5qHCmkvO4rkc
+neGfo+mMI+wQK+LpCGQ341+vXyybFlhltFjaNRTInazkX17KU04G39QVbft2HI0N
+s0VM/IjomgVMHFg0FztssvWRS/GxceWpRwIDAQAB
+-----END RSA PUBLIC KEY-----
+";
+
+    const EC_PUBLIC_KEY: &str = "\
+-----BEGIN PUBLIC KEY-----
+MFkwEwYHKoZIzj0CAQYIKoZIzj0DAQcDQgAEepAbnZSbwgAPtNxRsaPJ8KViawoP
+iQpolLKsJ95tG1xhQfppzFUaDYgSwu7CyuJfwjbZFHUP4fn9X8SHpWlWhA==
+-----END PUBLIC KEY-----
+";
+
+    const ED25519_PUBLIC_KEY: &str = "\
+-----BEGIN PUBLIC KEY-----
+MCowBQYDK2VwAyEAb6o5OOXtAXLXpuAJ+UTRYYxZqlLYfcaQdHsFM1nXCT4=
+-----END PUBLIC KEY-----
+";
+
+    fn jwk_json(algorithm: Algorithm, pem: &str) -> serde_json::Value {
+        serde_json::to_value(public_jwk(algorithm, "key-1", pem.as_bytes()).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn rsa_keys_publish_modulus_and_exponent() {
+        let n = concat!(
+            "sU4ch1gAS-fVaBsJ7Rn5cikXEJc_ogumRuDFBvocycbUJRG2b9BIcZLY887YvCfZ",
+            "kG3qkV3R0yVGlfUGhsLN5s-IO-r4IbRPUeECcBiNj7WtKBknHVjdH8QxITppU8HL",
+            "JzCh3xBCCN7JyZ8iiQiORZp5XNQtc9EGSemv5nVGOo-UCwmmX1hW8TOwHOEKl1G2",
+            "hRHRjqSVBl4e8vumVib4PcW2OT_S_FKez-lmlz9u5qHCmkvO4rkcneGfo-mMI-wQ",
+            "K-LpCGQ341-vXyybFlhltFjaNRTInazkX17KU04G39QVbft2HI0Ns0VM_IjomgVM",
+            "HFg0FztssvWRS_GxceWpRw",
+        );
+        for pem in [RSA_PUBLIC_KEY, RSA_PKCS1_PUBLIC_KEY] {
+            let jwk = jwk_json(Algorithm::RS256, pem);
+            assert_eq!(jwk["kty"], "RSA");
+            assert_eq!(jwk["alg"], "RS256");
+            assert_eq!(jwk["kid"], "key-1");
+            assert_eq!(jwk["use"], "sig");
+            assert_eq!(jwk["n"], n);
+            assert_eq!(jwk["e"], "AQAB");
+        }
+    }
+
+    #[test]
+    fn ec_keys_publish_both_coordinates() {
+        let jwk = jwk_json(Algorithm::ES256, EC_PUBLIC_KEY);
+        assert_eq!(jwk["kty"], "EC");
+        assert_eq!(jwk["crv"], "P-256");
+        assert_eq!(jwk["alg"], "ES256");
+        assert_eq!(jwk["x"], "epAbnZSbwgAPtNxRsaPJ8KViawoPiQpolLKsJ95tG1w");
+        assert_eq!(jwk["y"], "YUH6acxVGg2IEsLuwsriX8I22RR1D-H5_V_Eh6VpVoQ");
+    }
+
+    #[test]
+    fn ed25519_keys_publish_the_point() {
+        let jwk = jwk_json(Algorithm::EdDSA, ED25519_PUBLIC_KEY);
+        assert_eq!(jwk["kty"], "OKP");
+        assert_eq!(jwk["crv"], "Ed25519");
+        assert_eq!(jwk["alg"], "EdDSA");
+        assert_eq!(jwk["x"], "b6o5OOXtAXLXpuAJ-UTRYYxZqlLYfcaQdHsFM1nXCT4");
+    }
+
+    fn decode_with(
+        claims: serde_json::Value,
+        issuer: Option<&str>,
+        audience: Option<&str>,
+    ) -> jsonwebtoken::errors::Result<TokenData<serde_json::Value>> {
+        let token = encode(
+            &Header::new(Algorithm::HS256),
+            &claims,
+            &EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        decode(
+            &token,
+            &DecodingKey::from_secret(b"secret"),
+            &validation(Algorithm::HS256, issuer, audience),
+        )
+    }
+
+    fn claims(iss: Option<&str>, aud: Option<&str>) -> serde_json::Value {
+        let mut claims = serde_json::json!({
+            "sub": "user",
+            "exp": chrono::Utc::now().timestamp() + 60,
+        });
+        if let Some(iss) = iss {
+            claims["iss"] = iss.into();
+        }
+        if let Some(aud) = aud {
+            claims["aud"] = aud.into();
+        }
+        claims
+    }
+
+    #[test]
+    fn accepts_tokens_with_the_configured_issuer_and_audience() {
+        let issuer = Some("blog");
+        let audience = Some("readers");
+        assert!(decode_with(claims(issuer, audience), issuer, audience).is_ok());
+        assert!(decode_with(claims(None, None), None, None).is_ok());
+        assert!(decode_with(claims(issuer, None), issuer, None).is_ok());
+    }
+
+    #[test]
+    fn rejects_tokens_missing_a_configured_issuer_or_audience() {
+        let issuer = Some("blog");
+        let audience = Some("readers");
+        for claims in [
+            claims(None, audience),
+            claims(issuer, None),
+            claims(None, None),
+            claims(Some("elsewhere"), audience),
+            claims(issuer, Some("others")),
+        ] {
+            assert!(decode_with(claims, issuer, audience).is_err());
+        }
+        assert!(decode_with(claims(None, None), None, audience).is_err());
+    }
+
+    #[test]
+    fn rejects_keys_of_another_algorithm() {
+        assert!(public_jwk(Algorithm::ES256, "key-1", RSA_PUBLIC_KEY.as_bytes()).is_err());
+        assert!(public_jwk(Algorithm::EdDSA, "key-1", EC_PUBLIC_KEY.as_bytes()).is_err());
+        assert!(public_jwk(Algorithm::RS256, "key-1", ED25519_PUBLIC_KEY.as_bytes()).is_err());
+        assert!(public_jwk(Algorithm::RS256, "key-1", b"not a pem").is_err());
+        assert!(subject_public_key(&[0x30, 0x00]).is_err());
+    }
+}
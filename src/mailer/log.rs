@@ -0,0 +1,43 @@
+use async_trait::async_trait;
+use tokio::io::AsyncWriteExt;
+
+use super::{Email, Mailer, MailerError};
+
+/// Mailer for local development: appends emails to a file, or prints them to
+/// stdout when no path is configured.
+pub struct LogMailer {
+    path: Option<String>,
+}
+
+impl LogMailer {
+    pub fn new(path: Option<String>) -> LogMailer {
+        LogMailer { path }
+    }
+}
+
+#[async_trait]
+impl Mailer for LogMailer {
+    async fn send(&self, email: Email) -> Result<(), MailerError> {
+        let entry = format!(
+            "To: {}\nSubject: {}\n\n{}\n---\n",
+            email.to, email.subject, email.body
+        );
+
+        match &self.path {
+            Some(path) => {
+                let mut file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .map_err(|e| MailerError(e.to_string()))?;
+                file.write_all(entry.as_bytes())
+                    .await
+                    .map_err(|e| MailerError(e.to_string()))?;
+            }
+            None => println!("📧 {}", entry),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,40 @@
+pub mod log;
+pub mod smtp;
+
+use std::fmt;
+
+use async_trait::async_trait;
+
+use crate::config::Config;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+#[derive(Debug)]
+pub struct MailerError(pub String);
+
+impl fmt::Display for MailerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mailer error: {}", self.0)
+    }
+}
+
+impl std::error::Error for MailerError {}
+
+#[async_trait]
+pub trait Mailer: Send + Sync {
+    async fn send(&self, email: Email) -> Result<(), MailerError>;
+}
+
+/// Builds the mailer selected by `MAILER` ("smtp" or "log").
+pub fn from_config(config: &Config) -> Result<Box<dyn Mailer>, MailerError> {
+    match config.mailer.as_str() {
+        "smtp" => Ok(Box::new(smtp::SmtpMailer::new(config)?)),
+        "log" => Ok(Box::new(log::LogMailer::new(config.mail_log_path.clone()))),
+        other => Err(MailerError(format!("Unknown mailer: {}", other))),
+    }
+}
@@ -1,13 +1,27 @@
 mod config;
 mod handler;
 mod jwt_auth;
+mod jwt_keys;
+mod mailer;
 mod model;
+mod pagination;
+mod rbac;
+mod render;
 mod response;
+mod revocation;
 mod route;
+mod slug;
+mod spam;
+mod token;
 
 use config::Config;
+use jwt_keys::JwtKeys;
+use mailer::Mailer;
+use model::post::Post;
+use revocation::RevocationStore;
+use spam::SpamScorer;
 use tokio::net::TcpListener;
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use axum::http::{
     header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
@@ -22,13 +36,23 @@ use sqlx::{postgres::PgPoolOptions, Pool, Postgres};
 pub struct AppState {
     db: Pool<Postgres>,
     env: Config,
+    keys: JwtKeys,
+    revocations: RevocationStore,
+    mailer: Box<dyn Mailer>,
+    spam: SpamScorer,
 }
 
 #[tokio::main]
 async fn main() {
     dotenv().ok();
 
-    let config = Config::init();
+    let config = match Config::init() {
+        Ok(config) => config,
+        Err(err) => {
+            println!("🔥 Invalid configuration: {}", err);
+            std::process::exit(1);
+        }
+    };
 
     let pool = match PgPoolOptions::new()
         .max_connections(10)
@@ -45,17 +69,109 @@ async fn main() {
         }
     };
 
+    // every search language needs a text search configuration in the database
+    match sqlx::query_scalar!(
+        r#"SELECT cfgname::TEXT AS "name!" FROM pg_ts_config WHERE cfgname = ANY($1)"#,
+        &config.search_languages,
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(found) => {
+            for language in &config.search_languages {
+                if !found.contains(language) {
+                    println!("🔥 Unknown search language in SEARCH_LANGUAGES: {}", language);
+                    std::process::exit(1);
+                }
+            }
+        }
+        Err(err) => {
+            println!("🔥 Failed to check the search languages: {:?}", err);
+            std::process::exit(1);
+        }
+    }
+
+    let revocations = match RevocationStore::load(&pool).await {
+        Ok(revocations) => revocations,
+        Err(err) => {
+            println!("🔥 Failed to load revoked tokens: {:?}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let keys = match JwtKeys::from_config(&config) {
+        Ok(keys) => keys,
+        Err(err) => {
+            println!("🔥 Failed to load the JWT keys: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let mailer = match mailer::from_config(&config) {
+        Ok(mailer) => mailer,
+        Err(err) => {
+            println!("🔥 Failed to configure the mailer: {}", err);
+            std::process::exit(1);
+        }
+    };
+
+    let app_state = Arc::new(AppState {
+        db: pool.clone(),
+        env: config.clone(),
+        keys,
+        revocations,
+        mailer,
+        spam: spam::from_config(&config),
+    });
+
+    // pick up revocations made by other instances and drop expired entries
+    let sync_state = app_state.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            if let Err(err) = sync_state.revocations.sync(&sync_state.db).await {
+                eprintln!("Error syncing revoked tokens: {:?}", err);
+            }
+        }
+    });
+
+    // publish scheduled posts once their time has come
+    let scheduler_state = app_state.clone();
+    tokio::spawn(async move {
+        let period = scheduler_state
+            .env
+            .post_scheduler_interval
+            .to_std()
+            .unwrap_or(Duration::from_secs(30));
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            match Post::publish_due(&scheduler_state.db).await {
+                Ok(0) => {}
+                Ok(count) => println!("📰 Published {} scheduled post(s)", count),
+                Err(err) => eprintln!("Error publishing scheduled posts: {:?}", err),
+            }
+        }
+    });
+
+    // posts saved before their HTML was cached get it rendered once
+    let render_state = app_state.clone();
+    tokio::spawn(async move {
+        match Post::render_missing(&render_state.db).await {
+            Ok(0) => {}
+            Ok(count) => println!("📝 Rendered the HTML of {} post(s)", count),
+            Err(err) => eprintln!("Error rendering posts: {:?}", err),
+        }
+    });
+
     let cors = CorsLayer::new()
         .allow_origin("http://localhost:3000".parse::<HeaderValue>().unwrap())
-        .allow_methods([Method::GET, Method::POST, Method::PATCH, Method::DELETE])
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE])
         .allow_credentials(true)
         .allow_headers([AUTHORIZATION, ACCEPT, CONTENT_TYPE]);
 
-    let app = create_router(Arc::new(AppState {
-        db: pool.clone(),
-        env: config.clone(),
-    }))
-    .layer(cors);
+    let app = create_router(app_state).layer(cors);
 
     println!("🚀 Server started successfully");
     // run it with hyper
@@ -0,0 +1,74 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct AuditLog {
+    pub id: uuid::Uuid,
+    pub actor_id: Option<uuid::Uuid>,
+    pub action: String,
+    pub target_user_id: Option<uuid::Uuid>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+impl AuditLog {
+    pub async fn record(
+        actor_id: uuid::Uuid,
+        action: &str,
+        target_user_id: Option<uuid::Uuid>,
+        details: serde_json::Value,
+        db: impl sqlx::PgExecutor<'_>,
+    ) -> Result<AuditLog, sqlx::Error> {
+        let entry = sqlx::query_as!(
+            AuditLog,
+            r#"
+            INSERT INTO audit_logs (actor_id, action, target_user_id, details)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            actor_id,
+            action,
+            target_user_id,
+            details,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(entry)
+    }
+
+    pub async fn find_all(
+        target_user_id: Option<uuid::Uuid>,
+        page: usize,
+        per_page: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<AuditLog>, sqlx::Error> {
+        let per_page = per_page as i64;
+        let offset = (page as i64 - 1) * per_page;
+
+        let entries = sqlx::query_as!(
+            AuditLog,
+            r#"
+            SELECT *
+            FROM audit_logs
+            WHERE $1::UUID IS NULL OR target_user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2 OFFSET $3
+            "#,
+            target_user_id,
+            per_page,
+            offset,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(entries)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditLogQuery {
+    pub user_id: Option<uuid::Uuid>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
@@ -0,0 +1,160 @@
+use chrono::prelude::*;
+use serde::Deserialize;
+
+use crate::model::post::Post;
+
+/// A post a user saved to read later.
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub post_id: uuid::Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+impl Bookmark {
+    /// Bookmarks the post, a post that is already bookmarked keeps its
+    /// original date.
+    pub async fn insert(
+        user_id: uuid::Uuid,
+        post_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<Bookmark, sqlx::Error> {
+        let bookmark = sqlx::query_as!(
+            Bookmark,
+            r#"
+            INSERT INTO bookmarks (user_id, post_id)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id, post_id) DO UPDATE SET created_at = bookmarks.created_at
+            RETURNING post_id, created_at
+            "#,
+            user_id,
+            post_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(bookmark)
+    }
+
+    /// Returns whether there was a bookmark to remove.
+    pub async fn delete(
+        user_id: uuid::Uuid,
+        post_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            "DELETE FROM bookmarks WHERE user_id = $1 AND post_id = $2",
+            user_id,
+            post_id,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// The user's bookmarked posts, most recently bookmarked first. Posts that
+    /// were unpublished or deleted since are left out but keep their bookmark.
+    pub async fn find_posts(
+        user_id: uuid::Uuid,
+        after: (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool),
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(Bookmark, Post)>, sqlx::Error> {
+        let (created_at, post_id, backwards) = after;
+
+        let rows = sqlx::query!(
+            r#"
+            SELECT posts.id,
+                    posts.title,
+                    posts.slug,
+                    posts.content,
+                    posts.photo,
+                    posts.user_id,
+                    posts.created_at,
+                    posts.updated_at,
+                    posts.deleted_at,
+                    posts.status,
+                    posts.published_at,
+                    posts.search_language::TEXT AS "language!",
+                    posts.content_format,
+                    posts.content_html,
+                    posts.comments_locked,
+                    bookmarks.created_at AS bookmarked_at
+            FROM bookmarks
+            JOIN posts ON posts.id = bookmarks.post_id
+            WHERE bookmarks.user_id = $1
+                AND posts.deleted_at IS NULL
+                AND posts.status = 'published'
+                AND (
+                    $2::TIMESTAMPTZ IS NULL
+                    OR ($4 AND (bookmarks.created_at, bookmarks.post_id) > ($2, $3))
+                    OR (NOT $4 AND (bookmarks.created_at, bookmarks.post_id) < ($2, $3))
+                )
+            ORDER BY CASE WHEN $4 THEN bookmarks.created_at END,
+                CASE WHEN $4 THEN bookmarks.post_id END,
+                bookmarks.created_at DESC,
+                bookmarks.post_id DESC
+            LIMIT $5
+            "#,
+            user_id,
+            created_at,
+            post_id,
+            backwards,
+            limit as i64,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    Bookmark {
+                        post_id: row.id,
+                        created_at: row.bookmarked_at,
+                    },
+                    Post {
+                        id: row.id,
+                        title: row.title,
+                        slug: Some(row.slug),
+                        content: row.content,
+                        content_format: row.content_format,
+                        content_html: row.content_html,
+                        comments_locked: row.comments_locked,
+                        photo: row.photo,
+                        user_id: row.user_id,
+                        status: row.status,
+                        published_at: row.published_at,
+                        language: row.language,
+                        created_at: Some(row.created_at),
+                        updated_at: row.updated_at,
+                        deleted_at: row.deleted_at,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Which of the posts the user bookmarked.
+    pub async fn find_bookmarked(
+        user_id: uuid::Uuid,
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<uuid::Uuid>, sqlx::Error> {
+        let ids = sqlx::query_scalar!(
+            "SELECT post_id FROM bookmarks WHERE user_id = $1 AND post_id = ANY($2)",
+            user_id,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(ids)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct BookmarksQuery {
+    pub cursor: Option<String>,
+    pub per_page: Option<usize>,
+}
@@ -0,0 +1,544 @@
+use chrono::prelude::*;
+use serde::Deserialize;
+use std::fmt;
+
+use crate::render;
+
+/// A comment together with the name and photo of its author.
+#[derive(Debug, Clone)]
+pub struct Comment {
+    pub id: uuid::Uuid,
+    pub post_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    /// The comment this one replies to, `None` for top level comments.
+    pub parent_id: Option<uuid::Uuid>,
+    /// How many comments up the thread the top level comment is, 0 for top
+    /// level comments.
+    pub depth: i32,
+    /// Markdown as written by the author.
+    pub content: String,
+    /// Sanitized HTML rendered from `content` when the comment is saved.
+    pub content_html: String,
+    /// Moderation state, see `CommentStatus`.
+    pub status: String,
+    pub spam_score: f32,
+    pub author_name: String,
+    pub author_photo: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Moderation state of a comment, stored in `comments.status`. Only approved
+/// comments are shown on posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommentStatus {
+    Pending,
+    Approved,
+    Rejected,
+    Spam,
+}
+
+impl CommentStatus {
+    pub const ALL: [CommentStatus; 4] = [
+        CommentStatus::Pending,
+        CommentStatus::Approved,
+        CommentStatus::Rejected,
+        CommentStatus::Spam,
+    ];
+
+    pub fn parse(status: &str) -> Option<CommentStatus> {
+        CommentStatus::ALL
+            .into_iter()
+            .find(|s| s.as_str() == status)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommentStatus::Pending => "pending",
+            CommentStatus::Approved => "approved",
+            CommentStatus::Rejected => "rejected",
+            CommentStatus::Spam => "spam",
+        }
+    }
+}
+
+impl fmt::Display for CommentStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl Comment {
+    pub async fn insert(
+        post_id: uuid::Uuid,
+        user_id: uuid::Uuid,
+        parent_id: Option<uuid::Uuid>,
+        content: &str,
+        status: CommentStatus,
+        spam_score: f32,
+        db: &sqlx::PgPool,
+    ) -> Result<Comment, sqlx::Error> {
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            WITH comment AS (
+                INSERT INTO comments (
+                    post_id, user_id, parent_id, depth, content, content_html, status, spam_score
+                )
+                VALUES (
+                    $1,
+                    $2,
+                    $3,
+                    COALESCE((SELECT depth + 1 FROM comments WHERE id = $3), 0),
+                    $4,
+                    $5,
+                    $6,
+                    $7
+                )
+                RETURNING *
+            )
+            SELECT comment.id AS "id!",
+                    comment.post_id AS "post_id!",
+                    comment.user_id AS "user_id!",
+                    comment.parent_id,
+                    comment.depth AS "depth!",
+                    comment.content AS "content!",
+                    comment.content_html AS "content_html!",
+                    comment.status AS "status!",
+                    comment.spam_score AS "spam_score!",
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comment.created_at AS "created_at!",
+                    comment.updated_at AS "updated_at!",
+                    comment.deleted_at
+            FROM comment
+            JOIN users ON users.id = comment.user_id
+            "#,
+            post_id,
+            user_id,
+            parent_id,
+            content,
+            render::markdown_to_html(content),
+            status.as_str(),
+            spam_score,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    pub async fn get_by_id(id: uuid::Uuid, db: &sqlx::PgPool) -> Result<Comment, sqlx::Error> {
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT comments.id,
+                    comments.post_id,
+                    comments.user_id,
+                    comments.parent_id,
+                    comments.depth,
+                    comments.content,
+                    comments.content_html,
+                    comments.status,
+                    comments.spam_score,
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comments.created_at,
+                    comments.updated_at,
+                    comments.deleted_at
+            FROM comments
+            JOIN users ON users.id = comments.user_id
+            WHERE comments.id = $1 AND comments.deleted_at IS NULL
+            "#,
+            id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    pub async fn update(
+        id: uuid::Uuid,
+        content: &str,
+        status: CommentStatus,
+        spam_score: f32,
+        db: &sqlx::PgPool,
+    ) -> Result<Comment, sqlx::Error> {
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            WITH comment AS (
+                UPDATE comments
+                SET content = $2, content_html = $3, status = $4, spam_score = $5,
+                    updated_at = NOW()
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING *
+            )
+            SELECT comment.id AS "id!",
+                    comment.post_id AS "post_id!",
+                    comment.user_id AS "user_id!",
+                    comment.parent_id,
+                    comment.depth AS "depth!",
+                    comment.content AS "content!",
+                    comment.content_html AS "content_html!",
+                    comment.status AS "status!",
+                    comment.spam_score AS "spam_score!",
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comment.created_at AS "created_at!",
+                    comment.updated_at AS "updated_at!",
+                    comment.deleted_at
+            FROM comment
+            JOIN users ON users.id = comment.user_id
+            "#,
+            id,
+            content,
+            render::markdown_to_html(content),
+            status.as_str(),
+            spam_score,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    /// Deleted comments stay in the table so replies to them keep their place
+    /// in the thread.
+    pub async fn soft_delete(id: uuid::Uuid, db: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            "UPDATE comments SET deleted_at = NOW() WHERE id = $1 AND deleted_at IS NULL",
+            id,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Approved comments on a post, newest first, keyed on `(created_at, id)`
+    /// like `Post::find_by_user`. With `roots_only` only top level comments are
+    /// returned, including deleted ones that still have replies.
+    pub async fn find_by_post(
+        post_id: uuid::Uuid,
+        roots_only: bool,
+        after: (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool),
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let (created_at, id, backwards) = after;
+
+        let comments = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT comments.id,
+                    comments.post_id,
+                    comments.user_id,
+                    comments.parent_id,
+                    comments.depth,
+                    comments.content,
+                    comments.content_html,
+                    comments.status,
+                    comments.spam_score,
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comments.created_at,
+                    comments.updated_at,
+                    comments.deleted_at
+            FROM comments
+            JOIN users ON users.id = comments.user_id
+            WHERE comments.post_id = $1
+                AND comments.status = 'approved'
+                AND (NOT $2 OR comments.parent_id IS NULL)
+                AND (
+                    comments.deleted_at IS NULL
+                    OR (
+                        $2 AND EXISTS (
+                            SELECT 1
+                            FROM comments replies
+                            WHERE replies.parent_id = comments.id AND replies.status = 'approved'
+                        )
+                    )
+                )
+                AND (
+                    $3::TIMESTAMPTZ IS NULL
+                    OR ($5 AND (comments.created_at, comments.id) > ($3, $4))
+                    OR (NOT $5 AND (comments.created_at, comments.id) < ($3, $4))
+                )
+            ORDER BY CASE WHEN $5 THEN comments.created_at END,
+                CASE WHEN $5 THEN comments.id END,
+                comments.created_at DESC,
+                comments.id DESC
+            LIMIT $6
+            "#,
+            post_id,
+            roots_only,
+            created_at,
+            id,
+            backwards,
+            limit as i64,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(comments)
+    }
+
+    /// Every approved reply below the given comments up to `max_depth`, oldest
+    /// first.
+    pub async fn find_replies(
+        root_ids: &[uuid::Uuid],
+        max_depth: i32,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let comments = sqlx::query_as!(
+            Comment,
+            r#"
+            WITH RECURSIVE thread AS (
+                SELECT id FROM comments WHERE parent_id = ANY($1) AND status = 'approved'
+                UNION ALL
+                SELECT comments.id
+                FROM comments
+                JOIN thread ON comments.parent_id = thread.id
+                WHERE comments.status = 'approved' AND comments.depth <= $2
+            )
+            SELECT comments.id,
+                    comments.post_id,
+                    comments.user_id,
+                    comments.parent_id,
+                    comments.depth,
+                    comments.content,
+                    comments.content_html,
+                    comments.status,
+                    comments.spam_score,
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comments.created_at,
+                    comments.updated_at,
+                    comments.deleted_at
+            FROM comments
+            JOIN thread ON thread.id = comments.id
+            JOIN users ON users.id = comments.user_id
+            ORDER BY comments.created_at, comments.id
+            "#,
+            root_ids,
+            max_depth,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(comments)
+    }
+
+    /// Number of approved comments on each of the given posts, posts without
+    /// comments are left out.
+    pub async fn count_for_posts(
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(uuid::Uuid, i64)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT post_id, COUNT(*) AS "count!"
+            FROM comments
+            WHERE post_id = ANY($1) AND deleted_at IS NULL AND status = 'approved'
+            GROUP BY post_id
+            "#,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.post_id, row.count))
+            .collect())
+    }
+
+    /// Sets the moderation state of a comment on behalf of `moderator_id`.
+    pub async fn moderate(
+        id: uuid::Uuid,
+        status: CommentStatus,
+        moderator_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<Comment, sqlx::Error> {
+        let comment = sqlx::query_as!(
+            Comment,
+            r#"
+            WITH comment AS (
+                UPDATE comments
+                SET status = $2, moderated_by = $3, moderated_at = NOW()
+                WHERE id = $1 AND deleted_at IS NULL
+                RETURNING *
+            )
+            SELECT comment.id AS "id!",
+                    comment.post_id AS "post_id!",
+                    comment.user_id AS "user_id!",
+                    comment.parent_id,
+                    comment.depth AS "depth!",
+                    comment.content AS "content!",
+                    comment.content_html AS "content_html!",
+                    comment.status AS "status!",
+                    comment.spam_score AS "spam_score!",
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comment.created_at AS "created_at!",
+                    comment.updated_at AS "updated_at!",
+                    comment.deleted_at
+            FROM comment
+            JOIN users ON users.id = comment.user_id
+            "#,
+            id,
+            status.as_str(),
+            moderator_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(comment)
+    }
+
+    /// Comments waiting in the moderation queue with the given status, newest
+    /// first and keyed like `find_by_post`. With `post_author` only comments on
+    /// that user's posts are returned.
+    pub async fn find_for_moderation(
+        status: CommentStatus,
+        post_author: Option<uuid::Uuid>,
+        after: (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool),
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Comment>, sqlx::Error> {
+        let (created_at, id, backwards) = after;
+
+        let comments = sqlx::query_as!(
+            Comment,
+            r#"
+            SELECT comments.id,
+                    comments.post_id,
+                    comments.user_id,
+                    comments.parent_id,
+                    comments.depth,
+                    comments.content,
+                    comments.content_html,
+                    comments.status,
+                    comments.spam_score,
+                    users.name AS author_name,
+                    users.photo AS author_photo,
+                    comments.created_at,
+                    comments.updated_at,
+                    comments.deleted_at
+            FROM comments
+            JOIN users ON users.id = comments.user_id
+            JOIN posts ON posts.id = comments.post_id
+            WHERE comments.status = $1
+                AND comments.deleted_at IS NULL
+                AND posts.deleted_at IS NULL
+                AND ($2::UUID IS NULL OR posts.user_id = $2)
+                AND (
+                    $3::TIMESTAMPTZ IS NULL
+                    OR ($5 AND (comments.created_at, comments.id) > ($3, $4))
+                    OR (NOT $5 AND (comments.created_at, comments.id) < ($3, $4))
+                )
+            ORDER BY CASE WHEN $5 THEN comments.created_at END,
+                CASE WHEN $5 THEN comments.id END,
+                comments.created_at DESC,
+                comments.id DESC
+            LIMIT $6
+            "#,
+            status.as_str(),
+            post_author,
+            created_at,
+            id,
+            backwards,
+            limit as i64,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(comments)
+    }
+
+    /// When the user commented since `since`, for the spam rules.
+    pub async fn find_times_by_user(
+        user_id: uuid::Uuid,
+        since: DateTime<Utc>,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<DateTime<Utc>>, sqlx::Error> {
+        let times = sqlx::query_scalar!(
+            "SELECT created_at FROM comments WHERE user_id = $1 AND created_at > $2",
+            user_id,
+            since,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(times)
+    }
+
+    /// Whether the user had a comment other than `except` approved before,
+    /// first-time commenters go through moderation.
+    pub async fn has_approved_by_user(
+        user_id: uuid::Uuid,
+        except: Option<uuid::Uuid>,
+        db: &sqlx::PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let exists = sqlx::query_scalar!(
+            r#"
+            SELECT EXISTS (
+                SELECT 1
+                FROM comments
+                WHERE user_id = $1
+                    AND status = 'approved'
+                    AND ($2::UUID IS NULL OR id <> $2)
+            ) AS "exists!"
+            "#,
+            user_id,
+            except,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(exists)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCommentSchema {
+    pub content: String,
+    /// The comment being replied to.
+    pub parent_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateCommentSchema {
+    pub content: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CommentsQuery {
+    /// `tree` (the default) pages through top level comments with their
+    /// replies nested, `flat` pages through every comment.
+    pub view: Option<String>,
+    pub cursor: Option<String>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LockCommentsSchema {
+    pub locked: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerateCommentSchema {
+    /// `approve`, `reject` or `spam`.
+    pub action: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ModerationQuery {
+    /// `pending` (the default), `spam` or `rejected`.
+    pub status: Option<String>,
+    pub cursor: Option<String>,
+    pub per_page: Option<usize>,
+}
@@ -1,2 +1,10 @@
 pub mod user;
-pub mod post;
\ No newline at end of file
+pub mod post;
+pub mod post_revision;
+pub mod refresh_token;
+pub mod taxonomy;
+pub mod user_token;
+pub mod audit_log;
+pub mod comment;
+pub mod reaction;
+pub mod bookmark;
\ No newline at end of file
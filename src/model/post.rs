@@ -2,26 +2,158 @@ use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
 use std::fmt;
 
+use crate::{model::post_revision::PostRevision, render};
+
 #[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
 pub struct Post {
     pub id: uuid::Uuid,
     pub title: String,
     pub slug: Option<String>,
     pub content: String,
+    /// How `content` is written, see `ContentFormat`.
+    pub content_format: String,
+    /// Sanitized HTML rendered from `content` when the post is saved.
+    pub content_html: Option<String>,
+    /// Whether new comments are refused.
+    pub comments_locked: bool,
     pub photo: String,
     pub user_id: uuid::Uuid,
+    pub status: String,
+    pub published_at: Option<DateTime<Utc>>,
+    /// Text search configuration used to index the post, e.g. "english".
+    pub language: String,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+/// Publication state of a post, stored in `posts.status`. Only published posts
+/// are visible on the public endpoints.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PostStatus {
+    Draft,
+    Scheduled,
+    Published,
+    Archived,
+}
+
+impl PostStatus {
+    pub const ALL: [PostStatus; 4] = [
+        PostStatus::Draft,
+        PostStatus::Scheduled,
+        PostStatus::Published,
+        PostStatus::Archived,
+    ];
+
+    pub fn parse(status: &str) -> Option<PostStatus> {
+        PostStatus::ALL.into_iter().find(|s| s.as_str() == status)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PostStatus::Draft => "draft",
+            PostStatus::Scheduled => "scheduled",
+            PostStatus::Published => "published",
+            PostStatus::Archived => "archived",
+        }
+    }
+}
+
+impl fmt::Display for PostStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+/// Whose drafts and scheduled posts a listing shows next to the published
+/// posts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Drafts {
+    Own(uuid::Uuid),
+    All,
+}
+
+impl Drafts {
+    /// The `(include, author)` pair the listing queries filter on.
+    fn filter(drafts: Option<Drafts>) -> (bool, Option<uuid::Uuid>) {
+        match drafts {
+            Some(Drafts::Own(user_id)) => (true, Some(user_id)),
+            Some(Drafts::All) => (true, None),
+            None => (false, None),
+        }
+    }
+}
+
+/// How the content of a post is written, stored in `posts.content_format`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentFormat {
+    Markdown,
+    Html,
+}
+
+impl ContentFormat {
+    pub fn parse(format: &str) -> Option<ContentFormat> {
+        match format {
+            "markdown" => Some(ContentFormat::Markdown),
+            "html" => Some(ContentFormat::Html),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentFormat::Markdown => "markdown",
+            ContentFormat::Html => "html",
+        }
+    }
+
+    pub fn render(&self, content: &str) -> String {
+        match self {
+            ContentFormat::Markdown => render::markdown_to_html(content),
+            ContentFormat::Html => render::sanitize_html(content),
+        }
+    }
+}
+
+impl fmt::Display for ContentFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
 }
 
 impl Post {
-    pub async fn insert(post: Post, db: &sqlx::PgPool) -> Result<Post, sqlx::Error> {
+    pub fn is_published(&self) -> bool {
+        self.status == PostStatus::Published.as_str()
+    }
+
+    /// The `(created_at, id)` key post listings are paged on. Every post has
+    /// a `created_at`, unlike `published_at` which drafts may not have yet.
+    pub fn page_key(&self) -> (DateTime<Utc>, uuid::Uuid) {
+        (self.created_at.unwrap_or_default(), self.id)
+    }
+
+    /// Renders `content` to sanitized HTML according to its format.
+    pub fn render(&self) -> String {
+        ContentFormat::parse(&self.content_format)
+            .unwrap_or(ContentFormat::Markdown)
+            .render(&self.content)
+    }
+
+    /// Inserts the post along with its first revision, meant to run inside a
+    /// transaction.
+    pub async fn insert(post: Post, conn: &mut sqlx::PgConnection) -> Result<Post, sqlx::Error> {
+        let content_html = post.render();
         let post = sqlx::query_as!(
             Post,
             r#"
-            INSERT INTO posts (id, slug, title, content, photo, user_id)
-            VALUES ($1, $2, $3, $4, $5, $6)
-            RETURNING id, title, slug, content, photo, user_id, created_at, updated_at
+            INSERT INTO posts (
+                id, title, slug, content, photo, user_id, status, published_at, search_language,
+                content_format, content_html
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9::TEXT::REGCONFIG, $10, $11)
+            RETURNING id, title, slug, content, photo, user_id, created_at, updated_at, deleted_at,
+                status, published_at, search_language::TEXT AS "language!", content_format,
+                content_html, comments_locked
             "#,
             post.id,
             post.title,
@@ -29,8 +161,24 @@ impl Post {
             post.content,
             post.photo,
             post.user_id,
+            post.status,
+            post.published_at,
+            post.language,
+            post.content_format,
+            content_html,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        PostRevision::record(
+            post.id,
+            &post.title,
+            &post.content,
+            &post.content_format,
+            &post.photo,
+            post.user_id,
+            &mut *conn,
         )
-        .fetch_one(db)
         .await?;
 
         Ok(post)
@@ -47,7 +195,48 @@ impl Post {
                     photo,
                     user_id,
                     created_at,
-                    updated_at
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+            "#,
+            id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(post)
+    }
+
+    /// Like `get_by_id`, but also finds soft deleted posts.
+    pub async fn get_by_id_including_deleted(
+        id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<Post, sqlx::Error> {
+        let post = sqlx::query_as!(
+            Post,
+            r#"
+            SELECT id,
+                    title,
+                    content,
+                    slug,
+                    photo,
+                    user_id,
+                    created_at,
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
             FROM posts
             WHERE id = $1
             "#,
@@ -59,26 +248,202 @@ impl Post {
         Ok(post)
     }
 
-    pub async fn update(post: Post, db: &sqlx::PgPool) -> Result<Post, sqlx::Error> {
+    pub async fn get_by_slug(slug: &str, db: &sqlx::PgPool) -> Result<Post, sqlx::Error> {
+        let post = sqlx::query_as!(
+            Post,
+            r#"
+            SELECT id,
+                    title,
+                    content,
+                    slug,
+                    photo,
+                    user_id,
+                    created_at,
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
+            FROM posts
+            WHERE slug = $1 AND deleted_at IS NULL
+            "#,
+            slug,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(post)
+    }
+
+    /// Returns the canonical slug of the post that used to be reachable under `slug`.
+    pub async fn get_slug_redirect(
+        slug: &str,
+        db: &sqlx::PgPool,
+    ) -> Result<Option<String>, sqlx::Error> {
+        let slug = sqlx::query_scalar!(
+            r#"
+            SELECT posts.slug
+            FROM post_slug_history
+            JOIN posts ON posts.id = post_slug_history.post_id
+            WHERE post_slug_history.slug = $1
+                AND posts.deleted_at IS NULL
+                AND posts.status = 'published'
+            "#,
+            slug,
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(slug)
+    }
+
+    /// Returns `base` if no other post uses it yet, otherwise `base` with the next
+    /// free numeric suffix ("hello", "hello-2", "hello-3", ...). Slugs that other
+    /// posts used before are taken too, so old links keep pointing at them.
+    pub async fn next_available_slug(
+        base: &str,
+        post_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<String, sqlx::Error> {
+        let pattern = format!(
+            "{}-%",
+            base.replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_")
+        );
+
+        let taken: Vec<String> = sqlx::query_scalar!(
+            r#"
+            SELECT slug AS "slug!"
+            FROM posts
+            WHERE id <> $3 AND (slug = $1 OR slug LIKE $2)
+            UNION
+            SELECT slug
+            FROM post_slug_history
+            WHERE post_id <> $3 AND (slug = $1 OR slug LIKE $2)
+            "#,
+            base,
+            pattern,
+            post_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        if !taken.iter().any(|slug| slug == base) {
+            return Ok(base.to_string());
+        }
+
+        let highest = taken
+            .iter()
+            .filter_map(|slug| {
+                slug.strip_prefix(base)?
+                    .strip_prefix('-')?
+                    .parse::<u64>()
+                    .ok()
+            })
+            .max()
+            .unwrap_or(1);
+
+        Ok(format!("{}-{}", base, highest + 1))
+    }
+
+    /// Updates the post. When `post.slug` is `None` the current slug is kept,
+    /// otherwise the previous slug is moved to `post_slug_history`. Changes to
+    /// the title, content, format or photo are recorded as a new revision by
+    /// `editor_id`.
+    /// Meant to run inside a transaction, the post stays locked until it ends.
+    pub async fn update(
+        post: Post,
+        editor_id: uuid::Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<Post, sqlx::Error> {
+        let previous = sqlx::query!(
+            r#"
+            SELECT slug, title, content, content_format, photo
+            FROM posts
+            WHERE id = $1 AND deleted_at IS NULL
+            FOR UPDATE
+            "#,
+            post.id,
+        )
+        .fetch_one(&mut *conn)
+        .await?;
+
+        let content_html = post.render();
         let post = sqlx::query_as!(
             Post,
             r#"
             UPDATE posts
-            SET title = $1, content = $2, photo = $3, user_id = $4, updated_at = $5, slug = $6
-            WHERE id = $7
-            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug
+            SET title = $1, content = $2, photo = $3, user_id = $4, updated_at = $5,
+                slug = COALESCE($6, slug), status = $8, published_at = $9,
+                search_language = $10::TEXT::REGCONFIG, content_format = $11, content_html = $12
+            WHERE id = $7 AND deleted_at IS NULL
+            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug, deleted_at,
+                status, published_at, search_language::TEXT AS "language!", content_format,
+                content_html, comments_locked
             "#,
             post.title,
             post.content,
             post.photo,
             post.user_id,
             Utc::now(),
-            post.slug.unwrap(),
+            post.slug,
             post.id,
+            post.status,
+            post.published_at,
+            post.language,
+            post.content_format,
+            content_html,
         )
-        .fetch_one(db)
+        .fetch_one(&mut *conn)
         .await?;
 
+        if (
+            &post.title,
+            &post.content,
+            &post.content_format,
+            &post.photo,
+        ) != (
+            &previous.title,
+            &previous.content,
+            &previous.content_format,
+            &previous.photo,
+        ) {
+            PostRevision::record(
+                post.id,
+                &post.title,
+                &post.content,
+                &post.content_format,
+                &post.photo,
+                editor_id,
+                &mut *conn,
+            )
+            .await?;
+        }
+
+        let slug = post.slug.as_deref().unwrap_or_default();
+        if slug != previous.slug {
+            sqlx::query!(
+                r#"
+                INSERT INTO post_slug_history (slug, post_id)
+                VALUES ($1, $2)
+                ON CONFLICT (slug) DO UPDATE SET post_id = EXCLUDED.post_id, created_at = NOW()
+                "#,
+                previous.slug,
+                post.id,
+            )
+            .execute(&mut *conn)
+            .await?;
+
+            // the post may be going back to one of its earlier slugs
+            sqlx::query!("DELETE FROM post_slug_history WHERE slug = $1", slug)
+                .execute(&mut *conn)
+                .await?;
+        }
+
         Ok(post)
     }
 
@@ -88,7 +453,9 @@ impl Post {
             r#"
             DELETE FROM posts
             WHERE id = $1
-            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug
+            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug, deleted_at,
+                status, published_at, search_language::TEXT AS "language!", content_format,
+                content_html, comments_locked
             "#,
             id,
         )
@@ -98,14 +465,350 @@ impl Post {
         Ok(post)
     }
 
+    pub async fn soft_delete(id: uuid::Uuid, db: &sqlx::PgPool) -> Result<Post, sqlx::Error> {
+        let post = sqlx::query_as!(
+            Post,
+            r#"
+            UPDATE posts
+            SET deleted_at = NOW()
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug, deleted_at,
+                status, published_at, search_language::TEXT AS "language!", content_format,
+                content_html, comments_locked
+            "#,
+            id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(post)
+    }
+
+    /// Published posts, newest first. `tag` and `category` filter by slug, the
+    /// category filter includes posts in its subcategories.
+    ///
+    /// Pages are keyed on `(created_at, id)`, see `page_key`: `after` is the key
+    /// of the last post already seen, with `backwards` the posts before it are
+    /// returned oldest first instead.
     pub async fn find_all(
+        tag: Option<&str>,
+        category: Option<&str>,
+        drafts: Option<Drafts>,
+        after: (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool),
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let (created_at, id, backwards) = after;
+        let (include_drafts, drafts_author) = Drafts::filter(drafts);
+
+        let posts = sqlx::query_as!(
+            Post,
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM categories WHERE slug = $2
+                UNION ALL
+                SELECT categories.id
+                FROM categories
+                JOIN subtree ON categories.parent_id = subtree.id
+            )
+            SELECT id,
+                    title,
+                    slug,
+                    content,
+                    photo,
+                    user_id,
+                    created_at,
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
+            FROM posts
+            WHERE deleted_at IS NULL
+                AND (
+                    status = 'published'
+                    OR (
+                        $7
+                        AND status IN ('draft', 'scheduled')
+                        AND ($8::UUID IS NULL OR user_id = $8)
+                    )
+                )
+                AND (
+                    $1::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_tags
+                        JOIN tags ON tags.id = post_tags.tag_id
+                        WHERE post_tags.post_id = posts.id AND tags.slug = $1
+                    )
+                )
+                AND (
+                    $2::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_categories
+                        WHERE post_categories.post_id = posts.id
+                            AND post_categories.category_id IN (SELECT id FROM subtree)
+                    )
+                )
+                AND (
+                    $3::TIMESTAMPTZ IS NULL
+                    OR ($5 AND (created_at, id) > ($3, $4))
+                    OR (NOT $5 AND (created_at, id) < ($3, $4))
+                )
+            ORDER BY CASE WHEN $5 THEN created_at END,
+                CASE WHEN $5 THEN id END,
+                created_at DESC,
+                id DESC
+            LIMIT $6
+            "#,
+            tag,
+            category,
+            created_at,
+            id,
+            backwards,
+            limit as i64,
+            include_drafts,
+            drafts_author,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(posts)
+    }
+
+    /// Published posts with the most reactions first, paged by offset since
+    /// reaction counts change all the time. Filters like `find_all`.
+    pub async fn find_popular(
+        tag: Option<&str>,
+        category: Option<&str>,
+        offset: usize,
+        limit: usize,
         db: &sqlx::PgPool,
-        page: usize,
-        per_page: usize,
     ) -> Result<Vec<Post>, sqlx::Error> {
-        let page = page as i64;
-        let per_page = per_page as i64;
-        let offset = ((page - 1) * per_page) as i64;
+        let posts = sqlx::query_as!(
+            Post,
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM categories WHERE slug = $2
+                UNION ALL
+                SELECT categories.id
+                FROM categories
+                JOIN subtree ON categories.parent_id = subtree.id
+            )
+            SELECT id,
+                    title,
+                    slug,
+                    content,
+                    photo,
+                    user_id,
+                    created_at,
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
+            FROM posts
+            LEFT JOIN post_reaction_totals reactions ON reactions.post_id = posts.id
+            WHERE deleted_at IS NULL
+                AND status = 'published'
+                AND (
+                    $1::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_tags
+                        JOIN tags ON tags.id = post_tags.tag_id
+                        WHERE post_tags.post_id = posts.id AND tags.slug = $1
+                    )
+                )
+                AND (
+                    $2::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_categories
+                        WHERE post_categories.post_id = posts.id
+                            AND post_categories.category_id IN (SELECT id FROM subtree)
+                    )
+                )
+            ORDER BY COALESCE(reactions.total, 0) DESC, published_at DESC, id DESC
+            LIMIT $3 OFFSET $4
+            "#,
+            tag,
+            category,
+            limit as i64,
+            offset as i64,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(posts)
+    }
+
+    /// Number of posts `find_all` pages through.
+    pub async fn count_all(
+        tag: Option<&str>,
+        category: Option<&str>,
+        drafts: Option<Drafts>,
+        db: &sqlx::PgPool,
+    ) -> Result<i64, sqlx::Error> {
+        let (include_drafts, drafts_author) = Drafts::filter(drafts);
+        let total = sqlx::query_scalar!(
+            r#"
+            WITH RECURSIVE subtree AS (
+                SELECT id FROM categories WHERE slug = $2
+                UNION ALL
+                SELECT categories.id
+                FROM categories
+                JOIN subtree ON categories.parent_id = subtree.id
+            )
+            SELECT COUNT(*) AS "total!"
+            FROM posts
+            WHERE deleted_at IS NULL
+                AND (
+                    status = 'published'
+                    OR (
+                        $3
+                        AND status IN ('draft', 'scheduled')
+                        AND ($4::UUID IS NULL OR user_id = $4)
+                    )
+                )
+                AND (
+                    $1::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_tags
+                        JOIN tags ON tags.id = post_tags.tag_id
+                        WHERE post_tags.post_id = posts.id AND tags.slug = $1
+                    )
+                )
+                AND (
+                    $2::TEXT IS NULL
+                    OR EXISTS (
+                        SELECT 1
+                        FROM post_categories
+                        WHERE post_categories.post_id = posts.id
+                            AND post_categories.category_id IN (SELECT id FROM subtree)
+                    )
+                )
+            "#,
+            tag,
+            category,
+            include_drafts,
+            drafts_author,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(total)
+    }
+
+    /// Published posts matching a web-style search query ("rust -async", quoted
+    /// phrases, `or`), best matches first. Each post is matched in its own
+    /// language, `languages` limits which ones are searched.
+    pub async fn search(
+        query: &str,
+        languages: &[String],
+        offset: usize,
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(Post, SearchMatch)>, sqlx::Error> {
+        // the text is escaped so the highlighted snippets are safe to render as HTML
+        let rows = sqlx::query!(
+            r#"
+            WITH queries AS (
+                SELECT language, websearch_to_tsquery(language, $1) AS query
+                FROM UNNEST($2::TEXT[]::REGCONFIG[]) AS language
+            )
+            SELECT id,
+                    title,
+                    slug,
+                    content,
+                    photo,
+                    user_id,
+                    created_at,
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked,
+                    ts_rank_cd(search_vector, queries.query) AS "rank!",
+                    ts_headline(
+                        queries.language,
+                        replace(replace(replace(title, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                        queries.query,
+                        'StartSel=<mark>, StopSel=</mark>, HighlightAll=true'
+                    ) AS "highlighted_title!",
+                    ts_headline(
+                        queries.language,
+                        replace(replace(replace(content, '&', '&amp;'), '<', '&lt;'), '>', '&gt;'),
+                        queries.query,
+                        'StartSel=<mark>, StopSel=</mark>, MaxWords=35, MinWords=15, MaxFragments=2'
+                    ) AS "snippet!"
+            FROM posts
+            JOIN queries ON posts.search_language = queries.language
+                AND posts.search_vector @@ queries.query
+            WHERE deleted_at IS NULL AND status = 'published'
+            ORDER BY "rank!" DESC, published_at DESC, id
+            LIMIT $3 OFFSET $4
+            "#,
+            query,
+            languages,
+            limit as i64,
+            offset as i64,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    Post {
+                        id: row.id,
+                        title: row.title,
+                        slug: Some(row.slug),
+                        content: row.content,
+                        content_format: row.content_format,
+                        content_html: row.content_html,
+                        comments_locked: row.comments_locked,
+                        photo: row.photo,
+                        user_id: row.user_id,
+                        status: row.status,
+                        published_at: row.published_at,
+                        language: row.language,
+                        created_at: Some(row.created_at),
+                        updated_at: row.updated_at,
+                        deleted_at: row.deleted_at,
+                    },
+                    SearchMatch {
+                        rank: row.rank,
+                        highlighted_title: row.highlighted_title,
+                        snippet: row.snippet,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// All of a user's posts whatever their status, newest first. Pages are
+    /// keyed on `(created_at, id)` like `find_all`.
+    pub async fn find_by_user(
+        user_id: uuid::Uuid,
+        status: Option<PostStatus>,
+        after: (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool),
+        limit: usize,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Post>, sqlx::Error> {
+        let (created_at, id, backwards) = after;
 
         let posts = sqlx::query_as!(
             Post,
@@ -117,26 +820,179 @@ impl Post {
                     photo,
                     user_id,
                     created_at,
-                    updated_at
+                    updated_at,
+                    deleted_at,
+                    status,
+                    published_at,
+                    search_language::TEXT AS "language!",
+                    content_format,
+                    content_html,
+                    comments_locked
             FROM posts
-            ORDER BY created_at DESC
-            LIMIT $1 OFFSET $2
+            WHERE user_id = $1
+                AND deleted_at IS NULL
+                AND ($2::TEXT IS NULL OR status = $2)
+                AND (
+                    $3::TIMESTAMPTZ IS NULL
+                    OR ($5 AND (created_at, id) > ($3, $4))
+                    OR (NOT $5 AND (created_at, id) < ($3, $4))
+                )
+            ORDER BY CASE WHEN $5 THEN created_at END,
+                CASE WHEN $5 THEN id END,
+                created_at DESC,
+                id DESC
+            LIMIT $6
             "#,
-            per_page,
-            offset,
+            user_id,
+            status.map(|status| status.as_str()),
+            created_at,
+            id,
+            backwards,
+            limit as i64,
         )
         .fetch_all(db)
         .await?;
 
         Ok(posts)
     }
+
+    pub async fn count_by_user(
+        user_id: uuid::Uuid,
+        status: Option<PostStatus>,
+        db: &sqlx::PgPool,
+    ) -> Result<i64, sqlx::Error> {
+        let total = sqlx::query_scalar!(
+            r#"
+            SELECT COUNT(*) AS "total!"
+            FROM posts
+            WHERE user_id = $1 AND deleted_at IS NULL AND ($2::TEXT IS NULL OR status = $2)
+            "#,
+            user_id,
+            status.map(|status| status.as_str()),
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(total)
+    }
+
+    pub async fn set_comments_locked(
+        id: uuid::Uuid,
+        locked: bool,
+        db: &sqlx::PgPool,
+    ) -> Result<Post, sqlx::Error> {
+        let post = sqlx::query_as!(
+            Post,
+            r#"
+            UPDATE posts
+            SET comments_locked = $2
+            WHERE id = $1 AND deleted_at IS NULL
+            RETURNING id, title, content, photo, user_id, created_at, updated_at, slug, deleted_at,
+                status, published_at, search_language::TEXT AS "language!", content_format,
+                content_html, comments_locked
+            "#,
+            id,
+            locked,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(post)
+    }
+
+    /// Publishes scheduled posts whose time has come, returns how many were published.
+    pub async fn publish_due(db: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE posts
+            SET status = 'published', updated_at = NOW()
+            WHERE status = 'scheduled' AND published_at <= NOW() AND deleted_at IS NULL
+            "#,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Renders the HTML of posts saved before it was cached, returns how many
+    /// were rendered.
+    pub async fn render_missing(db: &sqlx::PgPool) -> Result<u64, sqlx::Error> {
+        let posts = sqlx::query!(
+            "SELECT id, content, content_format FROM posts WHERE content_html IS NULL",
+        )
+        .fetch_all(db)
+        .await?;
+
+        for post in &posts {
+            let html = ContentFormat::parse(&post.content_format)
+                .unwrap_or(ContentFormat::Markdown)
+                .render(&post.content);
+            sqlx::query!(
+                "UPDATE posts SET content_html = $1 WHERE id = $2",
+                html,
+                post.id,
+            )
+            .execute(db)
+            .await?;
+        }
+
+        Ok(posts.len() as u64)
+    }
+
+    /// Authors of the given posts, fetched in one query.
+    pub async fn find_authors(
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<PostAuthor>, sqlx::Error> {
+        let authors = sqlx::query_as!(
+            PostAuthor,
+            r#"
+            SELECT posts.id AS post_id, users.id, users.name, users.photo
+            FROM posts
+            JOIN users ON users.id = posts.user_id
+            WHERE posts.id = ANY($1)
+            "#,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(authors)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PostAuthor {
+    pub post_id: uuid::Uuid,
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub photo: String,
+}
+
+/// Why a post matched a search. `highlighted_title` and `snippet` are HTML
+/// escaped with the matching words wrapped in `<mark>`.
+#[derive(Debug, Serialize)]
+pub struct SearchMatch {
+    pub rank: f32,
+    pub highlighted_title: String,
+    pub snippet: String,
 }
 
 #[derive(Debug, Deserialize)]
 pub struct CreatePostSchema {
     pub title: String,
     pub content: String,
+    /// `markdown` (the default) or `html`.
+    pub content_format: Option<String>,
     pub photo: String,
+    pub status: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    /// Text search configuration, one of `SEARCH_LANGUAGES`.
+    pub language: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// Category slugs.
+    pub categories: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -144,7 +1000,16 @@ pub struct UpdatePostSchema {
     pub id: uuid::Uuid,
     pub title: String,
     pub content: String,
+    /// `markdown` (the default) or `html`.
+    pub content_format: Option<String>,
     pub photo: String,
+    pub status: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+    /// Text search configuration, one of `SEARCH_LANGUAGES`.
+    pub language: Option<String>,
+    pub tags: Option<Vec<String>>,
+    /// Category slugs.
+    pub categories: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -152,14 +1017,46 @@ pub struct DeletePostSchema {
     pub id: uuid::Uuid,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct DeletePostQuery {
+    pub hard: Option<bool>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct GetPostsPaginatedSchema {
-    pub page: Option<usize>,
+    pub cursor: Option<String>,
+    pub per_page: Option<usize>,
+    pub include_total: Option<bool>,
+    pub tag: Option<String>,
+    pub category: Option<String>,
+    /// `newest` (the default) or `popular`, by number of reactions.
+    pub sort: Option<String>,
+    /// Also list the drafts and scheduled posts the signed-in reader may edit.
+    pub include_drafts: Option<bool>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SearchPostsQuery {
+    pub q: String,
+    pub lang: Option<String>,
+    pub cursor: Option<String>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UserPostsQuery {
+    pub status: Option<String>,
+    pub cursor: Option<String>,
     pub per_page: Option<usize>,
+    pub include_total: Option<bool>,
 }
 
 impl fmt::Display for GetPostsPaginatedSchema {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "Page: {:?}, Per page: {:?}", self.page, self.per_page)
+        write!(
+            f,
+            "Cursor: {:?}, Per page: {:?}",
+            self.cursor, self.per_page
+        )
     }
 }
@@ -0,0 +1,113 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct PostRevision {
+    pub id: uuid::Uuid,
+    pub post_id: uuid::Uuid,
+    pub revision: i32,
+    pub title: String,
+    pub content: String,
+    /// How `content` is written, see `ContentFormat`.
+    pub content_format: String,
+    pub photo: String,
+    pub editor_id: Option<uuid::Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl PostRevision {
+    /// Stores the next revision of a post. Callers must hold a lock on the post
+    /// so concurrent edits don't get the same revision number.
+    pub async fn record(
+        post_id: uuid::Uuid,
+        title: &str,
+        content: &str,
+        content_format: &str,
+        photo: &str,
+        editor_id: uuid::Uuid,
+        db: impl sqlx::PgExecutor<'_>,
+    ) -> Result<PostRevision, sqlx::Error> {
+        let revision = sqlx::query_as!(
+            PostRevision,
+            r#"
+            INSERT INTO post_revisions (
+                post_id, revision, title, content, content_format, photo, editor_id
+            )
+            SELECT $1, COALESCE(MAX(revision), 0) + 1, $2, $3, $4, $5, $6
+            FROM post_revisions
+            WHERE post_id = $1
+            RETURNING *
+            "#,
+            post_id,
+            title,
+            content,
+            content_format,
+            photo,
+            editor_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(revision)
+    }
+
+    pub async fn find_all(
+        post_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<PostRevision>, sqlx::Error> {
+        let revisions = sqlx::query_as!(
+            PostRevision,
+            r#"
+            SELECT *
+            FROM post_revisions
+            WHERE post_id = $1
+            ORDER BY revision DESC
+            "#,
+            post_id,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(revisions)
+    }
+
+    pub async fn get(
+        post_id: uuid::Uuid,
+        revision: i32,
+        db: &sqlx::PgPool,
+    ) -> Result<PostRevision, sqlx::Error> {
+        let revision = sqlx::query_as!(
+            PostRevision,
+            "SELECT * FROM post_revisions WHERE post_id = $1 AND revision = $2",
+            post_id,
+            revision,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(revision)
+    }
+
+    /// The revision as a plain text document, used for diffs.
+    pub fn document(&self) -> String {
+        format!(
+            "Title: {}\nFormat: {}\nPhoto: {}\n\n{}\n",
+            self.title,
+            self.content_format,
+            self.photo,
+            self.content.trim_end_matches('\n')
+        )
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionPathSchema {
+    pub id: uuid::Uuid,
+    pub revision: i32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RevisionDiffQuery {
+    pub from: i32,
+    pub to: i32,
+}
@@ -0,0 +1,160 @@
+use serde::Deserialize;
+
+/// The reactions readers can leave on a post, stored in `post_reactions.reaction`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReactionKind {
+    /// 👍
+    ThumbsUp,
+    /// 👎
+    ThumbsDown,
+    /// 😄
+    Laugh,
+    /// 🎉
+    Hooray,
+    /// 😕
+    Confused,
+    /// ❤️
+    Heart,
+    /// 🚀
+    Rocket,
+    /// 👀
+    Eyes,
+}
+
+impl ReactionKind {
+    pub const ALL: [ReactionKind; 8] = [
+        ReactionKind::ThumbsUp,
+        ReactionKind::ThumbsDown,
+        ReactionKind::Laugh,
+        ReactionKind::Hooray,
+        ReactionKind::Confused,
+        ReactionKind::Heart,
+        ReactionKind::Rocket,
+        ReactionKind::Eyes,
+    ];
+
+    pub fn parse(reaction: &str) -> Option<ReactionKind> {
+        ReactionKind::ALL
+            .into_iter()
+            .find(|r| r.as_str() == reaction)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReactionKind::ThumbsUp => "thumbs_up",
+            ReactionKind::ThumbsDown => "thumbs_down",
+            ReactionKind::Laugh => "laugh",
+            ReactionKind::Hooray => "hooray",
+            ReactionKind::Confused => "confused",
+            ReactionKind::Heart => "heart",
+            ReactionKind::Rocket => "rocket",
+            ReactionKind::Eyes => "eyes",
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct PostReaction {
+    pub post_id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub reaction: ReactionKind,
+}
+
+impl PostReaction {
+    /// Adds the reaction, or takes it back when the user already reacted that
+    /// way. Returns whether the reaction is there afterwards. The counters in
+    /// `post_reaction_counts` and `post_reaction_totals` follow through a
+    /// trigger.
+    pub async fn toggle(&self, db: &sqlx::PgPool) -> Result<bool, sqlx::Error> {
+        let removed = sqlx::query!(
+            r#"
+            DELETE FROM post_reactions
+            WHERE post_id = $1 AND user_id = $2 AND reaction = $3
+            "#,
+            self.post_id,
+            self.user_id,
+            self.reaction.as_str(),
+        )
+        .execute(db)
+        .await?;
+
+        if removed.rows_affected() > 0 {
+            return Ok(false);
+        }
+
+        // a concurrent toggle may have added it in the meantime, that counts too
+        sqlx::query!(
+            r#"
+            INSERT INTO post_reactions (post_id, user_id, reaction)
+            VALUES ($1, $2, $3)
+            ON CONFLICT ON CONSTRAINT post_reactions_unique DO NOTHING
+            "#,
+            self.post_id,
+            self.user_id,
+            self.reaction.as_str(),
+        )
+        .execute(db)
+        .await?;
+
+        Ok(true)
+    }
+
+    /// The reactions the user left on the posts, as `(post_id, reaction)`.
+    pub async fn find_for_user(
+        user_id: uuid::Uuid,
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(uuid::Uuid, String)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT post_id, reaction
+            FROM post_reactions
+            WHERE user_id = $1 AND post_id = ANY($2)
+            ORDER BY created_at
+            "#,
+            user_id,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.post_id, row.reaction))
+            .collect())
+    }
+}
+
+#[derive(Debug)]
+pub struct ReactionCount {
+    pub post_id: uuid::Uuid,
+    pub reaction: String,
+    pub count: i32,
+}
+
+impl ReactionCount {
+    pub async fn find_for_posts(
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<ReactionCount>, sqlx::Error> {
+        let counts = sqlx::query_as!(
+            ReactionCount,
+            r#"
+            SELECT post_id, reaction, count
+            FROM post_reaction_counts
+            WHERE post_id = ANY($1) AND count > 0
+            "#,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(counts)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ReactionPathSchema {
+    pub id: uuid::Uuid,
+    pub reaction: String,
+}
@@ -0,0 +1,111 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct RefreshToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub family_id: uuid::Uuid,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    pub replaced_by: Option<uuid::Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RefreshToken {
+    pub async fn insert(
+        user_id: uuid::Uuid,
+        family_id: uuid::Uuid,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+        db: &sqlx::PgPool,
+    ) -> Result<RefreshToken, sqlx::Error> {
+        let token = sqlx::query_as!(
+            RefreshToken,
+            r#"
+            INSERT INTO refresh_tokens (user_id, family_id, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            user_id,
+            family_id,
+            token_hash,
+            expires_at,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(token)
+    }
+
+    pub async fn find_by_hash(
+        token_hash: &str,
+        db: &sqlx::PgPool,
+    ) -> Result<Option<RefreshToken>, sqlx::Error> {
+        let token = sqlx::query_as!(
+            RefreshToken,
+            "SELECT * FROM refresh_tokens WHERE token_hash = $1",
+            token_hash,
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(token)
+    }
+
+    /// Marks the token as used and points it at its successor. Returns `false`
+    /// when the token had already been revoked, which means it is being reused.
+    pub async fn rotate(
+        id: uuid::Uuid,
+        replaced_by: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW(), replaced_by = $1
+            WHERE id = $2 AND revoked_at IS NULL
+            "#,
+            replaced_by,
+            id,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() == 1)
+    }
+
+    pub async fn revoke_family(family_id: uuid::Uuid, db: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE family_id = $1 AND revoked_at IS NULL
+            "#,
+            family_id,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn revoke_all_for_user(
+        user_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            UPDATE refresh_tokens
+            SET revoked_at = NOW()
+            WHERE user_id = $1 AND revoked_at IS NULL
+            "#,
+            user_id,
+        )
+        .execute(db)
+        .await?;
+
+        Ok(())
+    }
+}
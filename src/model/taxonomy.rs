@@ -0,0 +1,334 @@
+use serde::{Deserialize, Serialize};
+
+use crate::slug::slugify_name;
+
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct Tag {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct Category {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<uuid::Uuid>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TagCount {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub slug: String,
+    pub post_count: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryCount {
+    pub id: uuid::Uuid,
+    pub name: String,
+    pub slug: String,
+    pub parent_id: Option<uuid::Uuid>,
+    /// Published posts filed directly under the category.
+    pub post_count: i64,
+    /// Published posts in the category or any of its subcategories.
+    pub total_post_count: i64,
+}
+
+/// Whether two tag or category names are the same apart from case and
+/// surrounding whitespace. Different names with the same slug, like "C++" and
+/// "C#", would otherwise end up as one.
+pub fn same_name(a: &str, b: &str) -> bool {
+    a.trim().to_lowercase() == b.trim().to_lowercase()
+}
+
+/// Tags and categories to set on a post, `None` keeps the current ones.
+#[derive(Debug, Default)]
+pub struct PostTaxonomy {
+    pub tags: Option<Vec<String>>,
+    pub category_ids: Option<Vec<uuid::Uuid>>,
+}
+
+impl PostTaxonomy {
+    pub async fn apply(
+        &self,
+        post_id: uuid::Uuid,
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        if let Some(tags) = &self.tags {
+            Tag::replace_for_post(post_id, tags, conn).await?;
+        }
+        if let Some(category_ids) = &self.category_ids {
+            Category::replace_for_post(post_id, category_ids, conn).await?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Tag {
+    /// Replaces the tags of a post, creating tags that don't exist yet. Tags are
+    /// matched by slug, so "Rust" and "rust" are the same tag. Names that
+    /// share a slug without being the same name are expected to be rejected
+    /// before, see `same_name`.
+    pub async fn replace_for_post(
+        post_id: uuid::Uuid,
+        names: &[String],
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        let mut tags: Vec<(String, String)> = Vec::new();
+        for name in names {
+            let slug = slugify_name(name);
+            if !tags.iter().any(|(_, s)| *s == slug) {
+                tags.push((name.trim().to_string(), slug));
+            }
+        }
+        let (names, slugs): (Vec<String>, Vec<String>) = tags.into_iter().unzip();
+
+        sqlx::query!(
+            r#"
+            INSERT INTO tags (name, slug)
+            SELECT * FROM UNNEST($1::TEXT[], $2::TEXT[])
+            ON CONFLICT (slug) DO NOTHING
+            "#,
+            &names,
+            &slugs,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        sqlx::query!("DELETE FROM post_tags WHERE post_id = $1", post_id)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO post_tags (post_id, tag_id)
+            SELECT $1, id FROM tags WHERE slug = ANY($2)
+            "#,
+            post_id,
+            &slugs,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    pub async fn find_by_slugs(
+        slugs: &[String],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Tag>, sqlx::Error> {
+        let tags = sqlx::query_as!(
+            Tag,
+            "SELECT id, name, slug FROM tags WHERE slug = ANY($1)",
+            slugs,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(tags)
+    }
+
+    /// Tags of the given posts as (post id, tag) pairs.
+    pub async fn find_for_posts(
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(uuid::Uuid, Tag)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT post_tags.post_id, tags.id, tags.name, tags.slug
+            FROM post_tags
+            JOIN tags ON tags.id = post_tags.tag_id
+            WHERE post_tags.post_id = ANY($1)
+            ORDER BY tags.name
+            "#,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.post_id,
+                    Tag {
+                        id: row.id,
+                        name: row.name,
+                        slug: row.slug,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Tags used by at least one published post, most used first.
+    pub async fn find_all_with_counts(db: &sqlx::PgPool) -> Result<Vec<TagCount>, sqlx::Error> {
+        let tags = sqlx::query_as!(
+            TagCount,
+            r#"
+            SELECT tags.id, tags.name, tags.slug, COUNT(*) AS "post_count!"
+            FROM tags
+            JOIN post_tags ON post_tags.tag_id = tags.id
+            JOIN posts ON posts.id = post_tags.post_id
+            WHERE posts.status = 'published' AND posts.deleted_at IS NULL
+            GROUP BY tags.id
+            ORDER BY COUNT(*) DESC, tags.name
+            "#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(tags)
+    }
+}
+
+impl Category {
+    pub async fn insert(
+        name: &str,
+        slug: &str,
+        parent_id: Option<uuid::Uuid>,
+        db: &sqlx::PgPool,
+    ) -> Result<Category, sqlx::Error> {
+        let category = sqlx::query_as!(
+            Category,
+            r#"
+            INSERT INTO categories (name, slug, parent_id)
+            VALUES ($1, $2, $3)
+            RETURNING id, name, slug, parent_id
+            "#,
+            name,
+            slug,
+            parent_id,
+        )
+        .fetch_one(db)
+        .await?;
+
+        Ok(category)
+    }
+
+    pub async fn find_by_slugs(
+        slugs: &[String],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<Category>, sqlx::Error> {
+        let categories = sqlx::query_as!(
+            Category,
+            "SELECT id, name, slug, parent_id FROM categories WHERE slug = ANY($1)",
+            slugs,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(categories)
+    }
+
+    pub async fn replace_for_post(
+        post_id: uuid::Uuid,
+        category_ids: &[uuid::Uuid],
+        conn: &mut sqlx::PgConnection,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM post_categories WHERE post_id = $1", post_id)
+            .execute(&mut *conn)
+            .await?;
+
+        sqlx::query!(
+            r#"
+            INSERT INTO post_categories (post_id, category_id)
+            SELECT $1, UNNEST($2::UUID[])
+            ON CONFLICT DO NOTHING
+            "#,
+            post_id,
+            category_ids,
+        )
+        .execute(&mut *conn)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Categories of the given posts as (post id, category) pairs.
+    pub async fn find_for_posts(
+        post_ids: &[uuid::Uuid],
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<(uuid::Uuid, Category)>, sqlx::Error> {
+        let rows = sqlx::query!(
+            r#"
+            SELECT post_categories.post_id, categories.id, categories.name, categories.slug,
+                categories.parent_id
+            FROM post_categories
+            JOIN categories ON categories.id = post_categories.category_id
+            WHERE post_categories.post_id = ANY($1)
+            ORDER BY categories.name
+            "#,
+            post_ids,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| {
+                (
+                    row.post_id,
+                    Category {
+                        id: row.id,
+                        name: row.name,
+                        slug: row.slug,
+                        parent_id: row.parent_id,
+                    },
+                )
+            })
+            .collect())
+    }
+
+    /// Every category with its published post counts, the front end builds the
+    /// tree from `parent_id`.
+    pub async fn find_all_with_counts(
+        db: &sqlx::PgPool,
+    ) -> Result<Vec<CategoryCount>, sqlx::Error> {
+        let categories = sqlx::query_as!(
+            CategoryCount,
+            r#"
+            WITH RECURSIVE subtree AS (
+                -- every category paired with itself and all of its descendants
+                SELECT id AS root_id, id FROM categories
+                UNION ALL
+                SELECT subtree.root_id, categories.id
+                FROM categories
+                JOIN subtree ON categories.parent_id = subtree.id
+            )
+            SELECT categories.id,
+                    categories.name,
+                    categories.slug,
+                    categories.parent_id,
+                    COUNT(DISTINCT posts.id) FILTER (
+                        WHERE post_categories.category_id = categories.id
+                    ) AS "post_count!",
+                    COUNT(DISTINCT posts.id) AS "total_post_count!"
+            FROM categories
+            JOIN subtree ON subtree.root_id = categories.id
+            LEFT JOIN post_categories ON post_categories.category_id = subtree.id
+            LEFT JOIN posts ON posts.id = post_categories.post_id
+                AND posts.status = 'published'
+                AND posts.deleted_at IS NULL
+            GROUP BY categories.id
+            ORDER BY categories.name
+            "#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        Ok(categories)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CreateCategorySchema {
+    pub name: String,
+    pub slug: Option<String>,
+    /// Slug of the parent category.
+    pub parent: Option<String>,
+}
@@ -13,13 +13,32 @@ pub struct User {
     pub verified: bool,
     pub created_at: Option<DateTime<Utc>>,
     pub updated_at: Option<DateTime<Utc>>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct TokenClaims {
     pub sub: String,
     pub iat: usize,
     pub exp: usize,
+    pub jti: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub iss: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aud: Option<String>,
+    /// `iat` in milliseconds, so revocations can tell apart tokens issued
+    /// within the same second.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iat_ms: Option<i64>,
+}
+
+impl TokenClaims {
+    /// When the token was issued in milliseconds. Tokens without `iat_ms`
+    /// predate it and count as issued at the start of their `iat` second.
+    pub fn issued_at_ms(&self) -> i64 {
+        self.iat_ms.unwrap_or(self.iat as i64 * 1000)
+    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -37,9 +56,9 @@ pub struct LoginUserSchema {
 
 #[derive(Debug, Deserialize)]
 pub struct UpdateUserSchema {
-    pub name: String,
-    pub email: String,
-    pub photo: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub photo: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -76,3 +95,23 @@ pub struct DeleteUserSchema {
     pub email: String,
     pub password: String,
 }
+
+#[derive(Debug, Deserialize)]
+pub struct AdminUsersQuery {
+    pub role: Option<String>,
+    pub verified: Option<bool>,
+    pub suspended: Option<bool>,
+    pub q: Option<String>,
+    pub page: Option<usize>,
+    pub per_page: Option<usize>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct UpdateRoleSchema {
+    pub role: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct SuspendUserSchema {
+    pub reason: String,
+}
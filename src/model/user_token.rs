@@ -0,0 +1,91 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub const VERIFY_EMAIL: &str = "verify_email";
+pub const RESET_PASSWORD: &str = "reset_password";
+
+/// A single-use token emailed to a user, stored hashed.
+#[derive(Debug, Deserialize, sqlx::FromRow, Serialize, Clone)]
+pub struct UserToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub purpose: String,
+    pub token_hash: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl UserToken {
+    /// Stores a new token, invalidating any outstanding token for the same purpose.
+    pub async fn insert(
+        user_id: uuid::Uuid,
+        purpose: &str,
+        token_hash: &str,
+        expires_at: DateTime<Utc>,
+        db: &sqlx::PgPool,
+    ) -> Result<UserToken, sqlx::Error> {
+        let mut tx = db.begin().await?;
+
+        sqlx::query!(
+            r#"
+            UPDATE user_tokens
+            SET used_at = NOW()
+            WHERE user_id = $1 AND purpose = $2 AND used_at IS NULL
+            "#,
+            user_id,
+            purpose,
+        )
+        .execute(&mut *tx)
+        .await?;
+
+        let token = sqlx::query_as!(
+            UserToken,
+            r#"
+            INSERT INTO user_tokens (user_id, purpose, token_hash, expires_at)
+            VALUES ($1, $2, $3, $4)
+            RETURNING *
+            "#,
+            user_id,
+            purpose,
+            token_hash,
+            expires_at,
+        )
+        .fetch_one(&mut *tx)
+        .await?;
+
+        tx.commit().await?;
+
+        Ok(token)
+    }
+
+    /// Marks a valid token as used and returns it. Returns `None` when the token
+    /// doesn't exist, belongs to someone else, was already used or has expired.
+    pub async fn consume(
+        user_id: uuid::Uuid,
+        purpose: &str,
+        token_hash: &str,
+        db: &sqlx::PgPool,
+    ) -> Result<Option<UserToken>, sqlx::Error> {
+        let token = sqlx::query_as!(
+            UserToken,
+            r#"
+            UPDATE user_tokens
+            SET used_at = NOW()
+            WHERE user_id = $1
+                AND purpose = $2
+                AND token_hash = $3
+                AND used_at IS NULL
+                AND expires_at > NOW()
+            RETURNING *
+            "#,
+            user_id,
+            purpose,
+            token_hash,
+        )
+        .fetch_optional(db)
+        .await?;
+
+        Ok(token)
+    }
+}
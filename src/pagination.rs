@@ -0,0 +1,494 @@
+use std::future::Future;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{prelude::*, Duration};
+use serde::Serialize;
+
+pub const DEFAULT_PER_PAGE: usize = 10;
+pub const MAX_PER_PAGE: usize = 100;
+
+/// Clamps the requested page size to `1..=MAX_PER_PAGE`.
+pub fn per_page(requested: Option<usize>) -> usize {
+    requested.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE)
+}
+
+/// A position in a listing, handed to clients as an opaque string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cursor {
+    /// Keyset position in a listing sorted by `(timestamp, id)` descending.
+    /// `backwards` cursors return the page before the position.
+    Key {
+        timestamp: DateTime<Utc>,
+        id: uuid::Uuid,
+        backwards: bool,
+    },
+    /// Plain offset, for listings without a stable sort key such as ranked
+    /// search results.
+    Offset(usize),
+}
+
+impl Cursor {
+    pub fn encode(&self) -> String {
+        let raw = match self {
+            Cursor::Key {
+                timestamp,
+                id,
+                backwards,
+            } => format!(
+                "{}:{}:{}",
+                if *backwards { "p" } else { "n" },
+                timestamp.timestamp_micros(),
+                id
+            ),
+            Cursor::Offset(offset) => format!("o:{}", offset),
+        };
+        URL_SAFE_NO_PAD.encode(raw)
+    }
+
+    pub fn decode(cursor: &str) -> Option<Cursor> {
+        let raw = String::from_utf8(URL_SAFE_NO_PAD.decode(cursor).ok()?).ok()?;
+        let mut parts = raw.split(':');
+        let cursor = match (parts.next()?, parts.next()?, parts.next()) {
+            ("o", offset, None) => Cursor::Offset(offset.parse().ok()?),
+            (direction @ ("n" | "p"), micros, Some(id)) => Cursor::Key {
+                timestamp: from_micros(micros.parse().ok()?)?,
+                id: id.parse().ok()?,
+                backwards: direction == "p",
+            },
+            _ => return None,
+        };
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(cursor)
+    }
+
+    /// The keyset position and direction to hand to a query, `None` for the
+    /// first page.
+    pub fn key(cursor: Option<Cursor>) -> (Option<DateTime<Utc>>, Option<uuid::Uuid>, bool) {
+        match cursor {
+            Some(Cursor::Key {
+                timestamp,
+                id,
+                backwards,
+            }) => (Some(timestamp), Some(id), backwards),
+            _ => (None, None, false),
+        }
+    }
+
+    /// The forward cursor from a backwards cursor's position, including the
+    /// row the cursor was taken from.
+    fn turned(self) -> Option<Cursor> {
+        let Cursor::Key {
+            timestamp,
+            id,
+            backwards: true,
+        } = self
+        else {
+            return None;
+        };
+
+        // Postgres orders UUIDs by their bytes, so moving the key up to the
+        // next UUID makes the position inclusive
+        let (timestamp, id) = match id.as_u128().checked_add(1) {
+            Some(next) => (timestamp, uuid::Uuid::from_u128(next)),
+            None => (timestamp + Duration::microseconds(1), uuid::Uuid::nil()),
+        };
+        Some(Cursor::Key {
+            timestamp,
+            id,
+            backwards: false,
+        })
+    }
+}
+
+/// Whether the listing goes on past a backwards `cursor`, that is whether the
+/// page before it has a next page. `fetch` runs the listing's query from the
+/// key it is given, it only needs to return one row.
+pub async fn continues<T, E, F>(
+    cursor: Option<Cursor>,
+    fetch: impl FnOnce((Option<DateTime<Utc>>, Option<uuid::Uuid>, bool)) -> F,
+) -> Result<bool, E>
+where
+    F: Future<Output = Result<Vec<T>, E>>,
+{
+    match cursor.and_then(Cursor::turned) {
+        Some(turned) => Ok(!fetch(Cursor::key(Some(turned))).await?.is_empty()),
+        None => Ok(false),
+    }
+}
+
+fn from_micros(micros: i64) -> Option<DateTime<Utc>> {
+    let seconds = micros.div_euclid(1_000_000);
+    let nanos = micros.rem_euclid(1_000_000) as u32 * 1_000;
+    Utc.timestamp_opt(seconds, nanos).single()
+}
+
+#[derive(Debug, Serialize)]
+pub struct PageLinks {
+    #[serde(rename = "self")]
+    pub current: String,
+    pub next: Option<String>,
+    pub prev: Option<String>,
+}
+
+/// Envelope for paginated listings.
+#[derive(Debug, Serialize)]
+pub struct Page<T> {
+    pub data: Vec<T>,
+    pub next_cursor: Option<String>,
+    pub prev_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+    pub links: PageLinks,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from rows fetched with `Cursor::key`, at most `per_page + 1`
+    /// of them so we can tell whether there is more to come. Backwards pages
+    /// arrive in ascending order and are flipped here, whether they have a next
+    /// page is up to `continues`.
+    pub fn keyset(
+        mut rows: Vec<T>,
+        per_page: usize,
+        cursor: Option<Cursor>,
+        continues: bool,
+        key: impl Fn(&T) -> (DateTime<Utc>, uuid::Uuid),
+        link: impl Fn(Option<&str>) -> String,
+    ) -> Page<T> {
+        let (_, _, backwards) = Cursor::key(cursor);
+        let has_more = rows.len() > per_page;
+        rows.truncate(per_page);
+        if backwards {
+            rows.reverse();
+        }
+
+        // going forwards there is something before us unless this is the first
+        // page, going backwards the rows after us may have gone since
+        let (has_next, has_prev) = if backwards {
+            (continues, has_more)
+        } else {
+            (has_more, cursor.is_some())
+        };
+
+        let cursor_at = |row: Option<&T>, backwards: bool| {
+            row.map(|row| {
+                let (timestamp, id) = key(row);
+                Cursor::Key {
+                    timestamp,
+                    id,
+                    backwards,
+                }
+                .encode()
+            })
+        };
+        let next_cursor = has_next.then(|| cursor_at(rows.last(), false)).flatten();
+        let prev_cursor = has_prev.then(|| cursor_at(rows.first(), true)).flatten();
+
+        Page::new(rows, next_cursor, prev_cursor, cursor, link)
+    }
+
+    /// Builds an offset page from at most `per_page + 1` rows.
+    pub fn offset(
+        mut rows: Vec<T>,
+        per_page: usize,
+        offset: usize,
+        link: impl Fn(Option<&str>) -> String,
+    ) -> Page<T> {
+        let has_more = rows.len() > per_page;
+        rows.truncate(per_page);
+
+        let next_cursor = has_more.then(|| Cursor::Offset(offset + per_page).encode());
+        let prev_cursor =
+            (offset > 0).then(|| Cursor::Offset(offset.saturating_sub(per_page)).encode());
+        let current = (offset > 0).then_some(Cursor::Offset(offset));
+
+        Page::new(rows, next_cursor, prev_cursor, current, link)
+    }
+
+    fn new(
+        data: Vec<T>,
+        next_cursor: Option<String>,
+        prev_cursor: Option<String>,
+        current: Option<Cursor>,
+        link: impl Fn(Option<&str>) -> String,
+    ) -> Page<T> {
+        let links = PageLinks {
+            current: link(current.map(|cursor| cursor.encode()).as_deref()),
+            next: next_cursor.as_deref().map(|cursor| link(Some(cursor))),
+            prev: prev_cursor.as_deref().map(|cursor| link(Some(cursor))),
+        };
+
+        Page {
+            data,
+            next_cursor,
+            prev_cursor,
+            total: None,
+            links,
+        }
+    }
+
+    pub fn with_total(self, total: Option<i64>) -> Page<T> {
+        Page { total, ..self }
+    }
+
+    /// Swaps the rows for another representation of them, in the same order.
+    pub fn with_data<U>(self, data: Vec<U>) -> Page<U> {
+        Page {
+            data,
+            next_cursor: self.next_cursor,
+            prev_cursor: self.prev_cursor,
+            total: self.total,
+            links: self.links,
+        }
+    }
+}
+
+/// Builds a link to `path` with the given query parameters plus the cursor.
+pub fn link(path: &str, params: &[(&str, Option<String>)], cursor: Option<&str>) -> String {
+    let query: Vec<String> = params
+        .iter()
+        .filter_map(|(name, value)| Some((name, value.as_deref()?)))
+        .chain(cursor.map(|cursor| (&"cursor", cursor)))
+        .map(|(name, value)| format!("{}={}", name, urlencoding::encode(value)))
+        .collect();
+
+    if query.is_empty() {
+        path.to_string()
+    } else {
+        format!("{}?{}", path, query.join("&"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::post::Post;
+
+    fn at(micros: i64, id: u128) -> (DateTime<Utc>, uuid::Uuid) {
+        (from_micros(micros).unwrap(), uuid::Uuid::from_u128(id))
+    }
+
+    /// `n` rows newest first, as a forward query returns them.
+    fn rows(newest: i64, n: i64) -> Vec<(DateTime<Utc>, uuid::Uuid)> {
+        (0..n)
+            .map(|i| at(newest - i, (newest - i) as u128))
+            .collect()
+    }
+
+    fn page(
+        rows: Vec<(DateTime<Utc>, uuid::Uuid)>,
+        cursor: Option<Cursor>,
+        continues: bool,
+    ) -> Page<(DateTime<Utc>, uuid::Uuid)> {
+        Page::keyset(
+            rows,
+            3,
+            cursor,
+            continues,
+            |row| *row,
+            |cursor| link("/items", &[], cursor),
+        )
+    }
+
+    fn key_at(row: (DateTime<Utc>, uuid::Uuid), backwards: bool) -> Cursor {
+        Cursor::Key {
+            timestamp: row.0,
+            id: row.1,
+            backwards,
+        }
+    }
+
+    fn decoded(cursor: &Option<String>) -> Option<Cursor> {
+        cursor
+            .as_deref()
+            .map(|cursor| Cursor::decode(cursor).unwrap())
+    }
+
+    #[test]
+    fn cursors_round_trip() {
+        let (timestamp, id) = at(1_700_000_000_123_456, 42);
+        for cursor in [
+            Cursor::Key {
+                timestamp,
+                id,
+                backwards: false,
+            },
+            Cursor::Key {
+                timestamp,
+                id,
+                backwards: true,
+            },
+            Cursor::Key {
+                timestamp: from_micros(-1).unwrap(),
+                id: uuid::Uuid::from_u128(u128::MAX),
+                backwards: false,
+            },
+            Cursor::Offset(0),
+            Cursor::Offset(120),
+        ] {
+            assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+        }
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        let id = uuid::Uuid::from_u128(42);
+        for raw in [
+            "",
+            "o",
+            "o:",
+            "o:-1",
+            "o:ten",
+            "o:1:2",
+            "x:1",
+            "n:1",
+            "q:1:00000000-0000-0000-0000-00000000002a",
+            "n:soon:00000000-0000-0000-0000-00000000002a",
+            "n:1:not-a-uuid",
+            &format!("p:1:{}:extra", id),
+            &format!("n:{}:{}", i64::MAX, id),
+        ] {
+            assert_eq!(
+                Cursor::decode(&URL_SAFE_NO_PAD.encode(raw)),
+                None,
+                "{}",
+                raw
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_base64_garbage() {
+        assert_eq!(Cursor::decode("not base64!"), None);
+        assert_eq!(Cursor::decode("bjox==="), None);
+        assert_eq!(
+            Cursor::decode(&URL_SAFE_NO_PAD.encode([0xff, 0xfe, 0x3a])),
+            None
+        );
+        assert_eq!(Cursor::decode("AAAA"), None);
+    }
+
+    #[test]
+    fn first_page_has_only_a_next_page() {
+        let page = page(rows(100, 4), None, false);
+        assert_eq!(page.data, rows(100, 3));
+        assert_eq!(decoded(&page.next_cursor), Some(key_at(at(98, 98), false)));
+        assert_eq!(page.prev_cursor, None);
+        assert_eq!(page.links.current, "/items");
+        assert_eq!(page.links.prev, None);
+
+        let page = self::page(rows(100, 3), None, false);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.prev_cursor, None);
+    }
+
+    #[test]
+    fn middle_page_has_both() {
+        let cursor = key_at(at(101, 101), false);
+        let page = page(rows(100, 4), Some(cursor), false);
+        assert_eq!(decoded(&page.next_cursor), Some(key_at(at(98, 98), false)));
+        assert_eq!(decoded(&page.prev_cursor), Some(key_at(at(100, 100), true)));
+        assert_eq!(
+            page.links.current,
+            format!("/items?cursor={}", cursor.encode())
+        );
+    }
+
+    #[test]
+    fn last_page_has_only_a_previous_page() {
+        let page = page(rows(100, 2), Some(key_at(at(101, 101), false)), false);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.links.next, None);
+        assert_eq!(decoded(&page.prev_cursor), Some(key_at(at(100, 100), true)));
+    }
+
+    #[test]
+    fn backwards_pages_are_flipped_and_ask_whether_the_listing_continues() {
+        let cursor = Some(key_at(at(97, 97), true));
+        // backwards queries return the rows oldest first
+        let mut ascending = rows(101, 4);
+        ascending.reverse();
+
+        let page = self::page(ascending.clone(), cursor, true);
+        assert_eq!(page.data, rows(100, 3));
+        assert_eq!(decoded(&page.next_cursor), Some(key_at(at(98, 98), false)));
+        assert_eq!(decoded(&page.prev_cursor), Some(key_at(at(100, 100), true)));
+
+        let page = self::page(ascending, cursor, false);
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(page.links.next, None);
+
+        // back on the first page
+        let mut ascending = rows(100, 3);
+        ascending.reverse();
+        let page = self::page(ascending, cursor, true);
+        assert_eq!(page.data, rows(100, 3));
+        assert_eq!(decoded(&page.next_cursor), Some(key_at(at(98, 98), false)));
+        assert_eq!(page.prev_cursor, None);
+    }
+
+    #[test]
+    fn turned_cursors_include_their_own_row() {
+        let (timestamp, id) = at(100, 41);
+        assert_eq!(key_at((timestamp, id), false).turned(), None);
+        assert_eq!(Cursor::Offset(3).turned(), None);
+        assert_eq!(
+            key_at((timestamp, id), true).turned(),
+            Some(key_at(at(100, 42), false))
+        );
+        assert_eq!(
+            key_at((timestamp, uuid::Uuid::from_u128(u128::MAX)), true).turned(),
+            Some(key_at(at(101, 0), false))
+        );
+    }
+
+    #[test]
+    fn posts_without_a_publication_date_page_on_their_creation() {
+        let post = |micros: i64, id: u128, published_at: Option<i64>| Post {
+            id: uuid::Uuid::from_u128(id),
+            title: String::new(),
+            slug: None,
+            content: String::new(),
+            content_format: "markdown".to_string(),
+            content_html: None,
+            comments_locked: false,
+            photo: String::new(),
+            user_id: uuid::Uuid::nil(),
+            status: if published_at.is_some() {
+                "published"
+            } else {
+                "draft"
+            }
+            .to_string(),
+            published_at: published_at.and_then(from_micros),
+            language: "english".to_string(),
+            created_at: from_micros(micros),
+            updated_at: None,
+            deleted_at: None,
+        };
+        let posts = vec![
+            post(100, 1, Some(500)),
+            post(99, 2, None),
+            post(98, 3, None),
+            post(97, 4, Some(50)),
+        ];
+
+        let page = Page::keyset(posts, 3, None, false, Post::page_key, |cursor| {
+            link("/api/posts", &[], cursor)
+        });
+        assert_eq!(decoded(&page.next_cursor), Some(key_at(at(98, 3), false)));
+
+        let cursor = key_at(at(99, 2), false);
+        let page = Page::keyset(
+            vec![post(98, 3, None)],
+            3,
+            Some(cursor),
+            false,
+            Post::page_key,
+            |cursor| link("/api/posts", &[], cursor),
+        );
+        assert_eq!(page.next_cursor, None);
+        assert_eq!(decoded(&page.prev_cursor), Some(key_at(at(98, 3), true)));
+    }
+}
@@ -0,0 +1,183 @@
+use std::{fmt, marker::PhantomData};
+
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{request::Parts, StatusCode},
+    Json,
+};
+use serde_json::json;
+
+use crate::model::user::User;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Admin,
+    Editor,
+    Author,
+    Reader,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    CreatePost,
+    UpdateOwnPost,
+    UpdateAnyPost,
+    DeleteOwnPost,
+    DeleteAnyPost,
+    CreateComment,
+    DeleteAnyComment,
+    ModerateComments,
+    React,
+    ManageCategories,
+    ManageUsers,
+}
+
+impl Role {
+    pub const ALL: [Role; 4] = [Role::Admin, Role::Editor, Role::Author, Role::Reader];
+
+    /// Parses a role as stored in `users.role`.
+    pub fn parse(role: &str) -> Option<Role> {
+        Role::ALL.into_iter().find(|r| r.as_str() == role)
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Admin => "admin",
+            Role::Editor => "editor",
+            Role::Author => "author",
+            Role::Reader => "reader",
+        }
+    }
+
+    pub fn permissions(&self) -> &'static [Permission] {
+        use Permission::*;
+
+        match self {
+            Role::Admin => &[
+                CreatePost,
+                UpdateOwnPost,
+                UpdateAnyPost,
+                DeleteOwnPost,
+                DeleteAnyPost,
+                CreateComment,
+                DeleteAnyComment,
+                ModerateComments,
+                React,
+                ManageCategories,
+                ManageUsers,
+            ],
+            Role::Editor => &[
+                CreatePost,
+                UpdateOwnPost,
+                UpdateAnyPost,
+                DeleteOwnPost,
+                DeleteAnyPost,
+                CreateComment,
+                DeleteAnyComment,
+                ModerateComments,
+                React,
+                ManageCategories,
+            ],
+            Role::Author => &[
+                CreatePost,
+                UpdateOwnPost,
+                DeleteOwnPost,
+                CreateComment,
+                React,
+            ],
+            Role::Reader => &[CreateComment, React],
+        }
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.permissions().contains(&permission)
+    }
+}
+
+impl fmt::Display for Role {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl User {
+    /// Unknown roles get the least privileged role.
+    pub fn role(&self) -> Role {
+        Role::parse(&self.role).unwrap_or(Role::Reader)
+    }
+
+    pub fn can(&self, permission: Permission) -> bool {
+        self.role().can(permission)
+    }
+}
+
+/// Marker types naming the permission an [`Authorized`] extractor requires.
+pub trait RequiredPermission {
+    const PERMISSION: Permission;
+}
+
+macro_rules! required_permissions {
+    ($($name:ident),* $(,)?) => {
+        $(
+            pub struct $name;
+
+            impl RequiredPermission for $name {
+                const PERMISSION: Permission = Permission::$name;
+            }
+        )*
+    };
+}
+
+pub mod require {
+    use super::{Permission, RequiredPermission};
+
+    required_permissions!(
+        CreatePost,
+        CreateComment,
+        React,
+        ManageCategories,
+        ManageUsers
+    );
+}
+
+/// Extracts the user put into the request extensions by `jwt_auth::auth`,
+/// rejecting the request with 403 unless their role grants `P`.
+///
+/// ```ignore
+/// pub async fn handler(Authorized(user, _): Authorized<require::CreatePost>) { ... }
+/// ```
+pub struct Authorized<P: RequiredPermission>(pub User, pub PhantomData<P>);
+
+#[async_trait]
+impl<S, P> FromRequestParts<S> for Authorized<P>
+where
+    S: Send + Sync,
+    P: RequiredPermission,
+{
+    type Rejection = (StatusCode, Json<serde_json::Value>);
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        let user = parts.extensions.get::<User>().cloned().ok_or_else(|| {
+            (
+                StatusCode::UNAUTHORIZED,
+                Json(json!({
+                    "status": "fail",
+                    "message": "You are not logged in, please provide token",
+                })),
+            )
+        })?;
+
+        if !user.can(P::PERMISSION) {
+            return Err((
+                StatusCode::FORBIDDEN,
+                Json(json!({
+                    "status": "fail",
+                    "message": "You don't have permission to perform this action",
+                })),
+            ));
+        }
+
+        Ok(Authorized(user, PhantomData))
+    }
+}
@@ -0,0 +1,190 @@
+use std::{borrow::Cow, sync::OnceLock};
+
+use ammonia::Builder;
+use pulldown_cmark::{html, CodeBlockKind, Event, Options, Parser, Tag, TagEnd};
+use syntect::{
+    highlighting::ThemeSet,
+    html::{css_for_theme_with_class_style, ClassStyle, ClassedHTMLGenerator},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+/// Highlighted code is marked up with `hl-` prefixed classes, styled by the
+/// stylesheet from `highlight_css`.
+const CLASS_PREFIX: &str = "hl-";
+const CLASS_STYLE: ClassStyle = ClassStyle::SpacedPrefixed {
+    prefix: CLASS_PREFIX,
+};
+const THEME: &str = "InspiredGitHub";
+
+/// Renders Markdown to sanitized HTML, highlighting fenced code blocks by
+/// their language.
+pub fn markdown_to_html(markdown: &str) -> String {
+    let options = Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH;
+
+    let mut events = Vec::new();
+    let mut code_block: Option<(String, Vec<Event>)> = None;
+    for event in Parser::new_ext(markdown, options) {
+        match event {
+            Event::Start(Tag::CodeBlock(ref kind)) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                code_block = Some((language, vec![event]));
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                if let Some((language, mut block)) = code_block.take() {
+                    let code: String = block
+                        .iter()
+                        .filter_map(|event| match event {
+                            Event::Text(text) => Some(text.as_ref()),
+                            _ => None,
+                        })
+                        .collect();
+                    match highlight(&code, &language) {
+                        Some(highlighted) => events.push(Event::Html(highlighted.into())),
+                        // leave it to pulldown-cmark to escape the code
+                        None => {
+                            block.push(event);
+                            events.extend(block);
+                        }
+                    }
+                }
+            }
+            event => match &mut code_block {
+                Some((_, block)) => block.push(event),
+                None => events.push(event),
+            },
+        }
+    }
+
+    let mut output = String::new();
+    html::push_html(&mut output, events.into_iter());
+    sanitize_html(&output)
+}
+
+/// Cleans HTML against the allowlist, dropping any tag, attribute or URL
+/// scheme that isn't on it.
+pub fn sanitize_html(html: &str) -> String {
+    sanitizer().clean(html).to_string()
+}
+
+/// Stylesheet for the classes used in highlighted code blocks.
+pub fn highlight_css() -> &'static str {
+    static CSS: OnceLock<String> = OnceLock::new();
+    CSS.get_or_init(|| {
+        let themes = ThemeSet::load_defaults();
+        css_for_theme_with_class_style(&themes.themes[THEME], CLASS_STYLE).unwrap_or_default()
+    })
+}
+
+fn highlight(code: &str, language: &str) -> Option<String> {
+    let syntaxes = syntax_set();
+    let syntax = syntaxes
+        .find_syntax_by_token(language)
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text());
+
+    let mut generator = ClassedHTMLGenerator::new_with_class_style(syntax, syntaxes, CLASS_STYLE);
+    for line in LinesWithEndings::from(code) {
+        generator
+            .parse_html_for_line_which_includes_newline(line)
+            .ok()?;
+    }
+
+    Some(format!(
+        "<pre class=\"{}code\"><code>{}</code></pre>\n",
+        CLASS_PREFIX,
+        generator.finalize()
+    ))
+}
+
+fn syntax_set() -> &'static SyntaxSet {
+    static SYNTAXES: OnceLock<SyntaxSet> = OnceLock::new();
+    SYNTAXES.get_or_init(SyntaxSet::load_defaults_newlines)
+}
+
+/// ammonia's default allowlist, plus the highlighting classes on code blocks.
+fn sanitizer() -> &'static Builder<'static> {
+    static SANITIZER: OnceLock<Builder<'static>> = OnceLock::new();
+    SANITIZER.get_or_init(|| {
+        let mut builder = Builder::default();
+        builder
+            .add_tag_attributes("pre", &["class"])
+            .add_tag_attributes("span", &["class"])
+            .attribute_filter(|_, attribute, value| {
+                if attribute != "class" {
+                    return Some(Cow::Borrowed(value));
+                }
+                let classes: Vec<&str> = value
+                    .split_whitespace()
+                    .filter(|class| class.starts_with(CLASS_PREFIX))
+                    .collect();
+                (!classes.is_empty()).then(|| Cow::Owned(classes.join(" ")))
+            });
+        builder
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_scripts_and_event_handlers() {
+        let html = sanitize_html("<p>hi</p><script>alert(1)</script><img src=x onerror=alert(1)>");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("alert(1)"));
+        assert!(!html.contains("onerror"));
+        assert!(html.contains("<p>hi</p>"));
+
+        let html = markdown_to_html("hi <script>alert(1)</script>\n\n<b onclick=\"x()\">b</b>");
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("onclick"));
+    }
+
+    #[test]
+    fn drops_javascript_links() {
+        let html = markdown_to_html("[click](javascript:alert(1))");
+        assert!(!html.contains("javascript:"));
+        assert!(html.contains("click"));
+
+        let html = sanitize_html("<a href=\"JavaScript:alert(1)\">x</a>");
+        assert!(!html.to_lowercase().contains("javascript:"));
+
+        let html = markdown_to_html("[ok](https://example.com)");
+        assert!(html.contains("href=\"https://example.com\""));
+    }
+
+    #[test]
+    fn keeps_only_highlighting_classes() {
+        let html = sanitize_html(
+            "<pre class=\"hl-code evil\"><span class=\"x hl-keyword\">a</span></pre>",
+        );
+        assert!(html.contains("class=\"hl-code\""));
+        assert!(html.contains("class=\"hl-keyword\""));
+        assert!(!html.contains("evil"));
+
+        let html = sanitize_html("<span class=\"evil\">a</span><p class=\"hl-code\">b</p>");
+        assert!(!html.contains("class"));
+    }
+
+    #[test]
+    fn highlights_fenced_code() {
+        let html = markdown_to_html("```rust\nfn main() {}\n```");
+        assert!(html.contains("<pre class=\"hl-code\"><code>"));
+        assert!(html.contains("<span class=\"hl-"));
+        assert!(html.contains("main"));
+    }
+
+    #[test]
+    fn unknown_languages_are_escaped_plain_text() {
+        let html = markdown_to_html("```nosuchlanguage\n<b>bold</b> & more\n```");
+        assert!(html.contains("&lt;b&gt;bold&lt;/b&gt; &amp; more"));
+        assert!(!html.contains("<b>"));
+    }
+}
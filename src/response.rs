@@ -1,6 +1,14 @@
+use std::collections::BTreeMap;
+
 use chrono::prelude::*;
 use serde::Serialize;
 
+use crate::model::{
+    comment::Comment,
+    post::{Post, SearchMatch},
+    taxonomy::{Category, Tag},
+};
+
 #[allow(non_snake_case)]
 #[derive(Debug, Serialize)]
 pub struct FilteredUser {
@@ -12,6 +20,8 @@ pub struct FilteredUser {
     pub verified: bool,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub suspended_at: Option<DateTime<Utc>>,
+    pub suspension_reason: Option<String>,
 }
 
 #[derive(Serialize, Debug)]
@@ -24,3 +34,147 @@ pub struct UserResponse {
     pub status: String,
     pub data: UserData,
 }
+
+/// The public part of a `FilteredUser`, shown as the author of a post.
+#[derive(Serialize, Debug, Clone)]
+pub struct AuthorSummary {
+    pub id: String,
+    pub name: String,
+    pub photo: String,
+}
+
+/// A post as returned by every post endpoint, with its author, tags and
+/// categories.
+#[derive(Serialize, Debug)]
+pub struct PostResponse {
+    pub id: uuid::Uuid,
+    pub title: String,
+    pub slug: Option<String>,
+    pub content: String,
+    pub content_format: String,
+    /// Sanitized HTML rendered from `content`.
+    pub content_html: String,
+    pub photo: String,
+    pub status: String,
+    pub published_at: Option<DateTime<Utc>>,
+    pub language: String,
+    pub created_at: Option<DateTime<Utc>>,
+    pub updated_at: Option<DateTime<Utc>>,
+    pub author: AuthorSummary,
+    pub tags: Vec<Tag>,
+    pub categories: Vec<Category>,
+    pub comment_count: i64,
+    pub comments_locked: bool,
+    /// Number of reactions of each kind, kinds nobody used are left out.
+    pub reactions: BTreeMap<String, i32>,
+    /// Whether the requesting user bookmarked the post, only present when the
+    /// request is authenticated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_bookmarked: Option<bool>,
+    /// The reactions the requesting user left, only present when the request
+    /// is authenticated.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub my_reactions: Option<Vec<String>>,
+}
+
+impl PostResponse {
+    pub fn new(
+        post: Post,
+        author: AuthorSummary,
+        tags: Vec<Tag>,
+        categories: Vec<Category>,
+        comment_count: i64,
+        reactions: BTreeMap<String, i32>,
+    ) -> PostResponse {
+        PostResponse {
+            id: post.id,
+            title: post.title,
+            slug: post.slug,
+            content: post.content,
+            content_format: post.content_format,
+            content_html: post.content_html.unwrap_or_default(),
+            photo: post.photo,
+            status: post.status,
+            published_at: post.published_at,
+            language: post.language,
+            created_at: post.created_at,
+            updated_at: post.updated_at,
+            author,
+            tags,
+            categories,
+            comment_count,
+            comments_locked: post.comments_locked,
+            reactions,
+            is_bookmarked: None,
+            my_reactions: None,
+        }
+    }
+}
+
+/// A comment as returned by the comment endpoints. Deleted comments that are
+/// kept for their replies have no content or author.
+#[derive(Serialize, Debug)]
+pub struct CommentResponse {
+    pub id: uuid::Uuid,
+    pub post_id: uuid::Uuid,
+    pub parent_id: Option<uuid::Uuid>,
+    pub content: Option<String>,
+    pub content_html: Option<String>,
+    pub author: Option<AuthorSummary>,
+    pub status: String,
+    pub deleted: bool,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    /// Nested replies, only present in the tree view.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replies: Option<Vec<CommentResponse>>,
+}
+
+/// A comment in the moderation queue.
+#[derive(Serialize, Debug)]
+pub struct QueuedComment {
+    #[serde(flatten)]
+    pub comment: CommentResponse,
+    pub spam_score: f32,
+}
+
+impl From<Comment> for CommentResponse {
+    fn from(comment: Comment) -> CommentResponse {
+        let deleted = comment.deleted_at.is_some();
+        let author = AuthorSummary {
+            id: comment.user_id.to_string(),
+            name: comment.author_name,
+            photo: comment.author_photo,
+        };
+
+        CommentResponse {
+            id: comment.id,
+            post_id: comment.post_id,
+            parent_id: comment.parent_id,
+            content: (!deleted).then_some(comment.content),
+            content_html: (!deleted).then_some(comment.content_html),
+            author: (!deleted).then_some(author),
+            status: comment.status,
+            deleted,
+            created_at: comment.created_at,
+            updated_at: comment.updated_at,
+            replies: None,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SearchResult {
+    #[serde(flatten)]
+    pub post: PostResponse,
+    #[serde(flatten)]
+    pub search: SearchMatch,
+}
+
+/// A post in the user's bookmarks.
+#[derive(Serialize, Debug)]
+pub struct BookmarkedPost {
+    #[serde(flatten)]
+    pub post: PostResponse,
+    pub bookmarked_at: DateTime<Utc>,
+}
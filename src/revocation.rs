@@ -0,0 +1,162 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use chrono::prelude::*;
+
+/// Revoked access tokens, persisted in Postgres and cached in memory so the
+/// `auth` middleware doesn't hit the database on every request.
+///
+/// The cache is written through on every revocation and re-synced from the
+/// database periodically, so revocations made by other server instances are
+/// picked up within one sync interval.
+#[derive(Debug, Default)]
+pub struct RevocationStore {
+    // jti -> expiry of the revoked token
+    tokens: RwLock<HashMap<uuid::Uuid, DateTime<Utc>>>,
+    // user id -> tokens issued before this instant are revoked
+    users: RwLock<HashMap<uuid::Uuid, DateTime<Utc>>>,
+}
+
+impl RevocationStore {
+    pub async fn load(db: &sqlx::PgPool) -> Result<RevocationStore, sqlx::Error> {
+        let store = RevocationStore::default();
+        store.sync(db).await?;
+        Ok(store)
+    }
+
+    /// Drops expired revocations and reloads the cache from the database.
+    pub async fn sync(&self, db: &sqlx::PgPool) -> Result<(), sqlx::Error> {
+        sqlx::query!("DELETE FROM revoked_tokens WHERE expires_at < NOW()")
+            .execute(db)
+            .await?;
+
+        let tokens = sqlx::query!("SELECT jti, expires_at FROM revoked_tokens")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| (row.jti, row.expires_at))
+            .collect();
+
+        let users = sqlx::query!("SELECT user_id, revoked_before FROM user_token_revocations")
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .map(|row| (row.user_id, row.revoked_before))
+            .collect();
+
+        *self.tokens.write().unwrap() = tokens;
+        *self.users.write().unwrap() = users;
+
+        Ok(())
+    }
+
+    /// `issued_at_ms` is the token's issue time in milliseconds, see
+    /// `TokenClaims::issued_at_ms`. Tokens issued in the same millisecond as
+    /// a revocation stay valid, so a token handed out right after revoking the
+    /// user's sessions keeps working.
+    pub fn is_revoked(&self, jti: uuid::Uuid, user_id: uuid::Uuid, issued_at_ms: i64) -> bool {
+        if self.tokens.read().unwrap().contains_key(&jti) {
+            return true;
+        }
+
+        match self.users.read().unwrap().get(&user_id) {
+            Some(revoked_before) => issued_at_ms < revoked_before.timestamp_millis(),
+            None => false,
+        }
+    }
+
+    pub async fn revoke_token(
+        &self,
+        jti: uuid::Uuid,
+        user_id: uuid::Uuid,
+        expires_at: DateTime<Utc>,
+        db: &sqlx::PgPool,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query!(
+            r#"
+            INSERT INTO revoked_tokens (jti, user_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (jti) DO NOTHING
+            "#,
+            jti,
+            user_id,
+            expires_at,
+        )
+        .execute(db)
+        .await?;
+
+        self.tokens.write().unwrap().insert(jti, expires_at);
+
+        Ok(())
+    }
+
+    /// Revokes every access token issued to the user up to now.
+    pub async fn revoke_user(
+        &self,
+        user_id: uuid::Uuid,
+        db: &sqlx::PgPool,
+    ) -> Result<(), sqlx::Error> {
+        // taken from the same clock as the tokens' `iat_ms`, and cut to what
+        // Postgres stores so a re-sync doesn't move it
+        let revoked_before = Utc::now().trunc_subsecs(6);
+
+        sqlx::query!(
+            r#"
+            INSERT INTO user_token_revocations (user_id, revoked_before)
+            VALUES ($1, $2)
+            ON CONFLICT (user_id) DO UPDATE SET revoked_before = EXCLUDED.revoked_before
+            "#,
+            user_id,
+            revoked_before,
+        )
+        .execute(db)
+        .await?;
+
+        self.users.write().unwrap().insert(user_id, revoked_before);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn revoked_at(user_id: uuid::Uuid, revoked_before: DateTime<Utc>) -> RevocationStore {
+        let store = RevocationStore::default();
+        store.users.write().unwrap().insert(user_id, revoked_before);
+        store
+    }
+
+    #[test]
+    fn tokens_issued_right_after_a_revocation_stay_valid() {
+        let user_id = uuid::Uuid::new_v4();
+        let store = revoked_at(user_id, Utc::now().trunc_subsecs(6));
+
+        let issued_at_ms = Utc::now().timestamp_millis();
+        assert!(!store.is_revoked(uuid::Uuid::new_v4(), user_id, issued_at_ms));
+    }
+
+    #[test]
+    fn tokens_issued_before_a_revocation_are_revoked() {
+        let user_id = uuid::Uuid::new_v4();
+        let revoked_before = Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+            + chrono::Duration::microseconds(1_500);
+        let store = revoked_at(user_id, revoked_before);
+        let revoked_ms = revoked_before.timestamp_millis();
+
+        assert!(store.is_revoked(uuid::Uuid::new_v4(), user_id, revoked_ms - 1));
+        assert!(!store.is_revoked(uuid::Uuid::new_v4(), user_id, revoked_ms));
+        assert!(!store.is_revoked(uuid::Uuid::new_v4(), user_id, revoked_ms + 1));
+        // other users are unaffected
+        assert!(!store.is_revoked(uuid::Uuid::new_v4(), uuid::Uuid::new_v4(), 0));
+    }
+
+    #[test]
+    fn revoked_tokens_stay_revoked() {
+        let store = RevocationStore::default();
+        let jti = uuid::Uuid::new_v4();
+        store.tokens.write().unwrap().insert(jti, Utc::now());
+
+        assert!(store.is_revoked(jti, uuid::Uuid::new_v4(), Utc::now().timestamp_millis()));
+    }
+}
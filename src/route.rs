@@ -2,35 +2,107 @@ use std::sync::Arc;
 
 use axum::{
     middleware,
-    routing::{get, post},
+    routing::{delete, get, patch, post, put},
     Router,
 };
 
 use crate::{
+    handler::admin::{
+        delete_user_handler, force_password_reset_handler, get_user_handler,
+        list_audit_logs_handler, list_users_handler, suspend_user_handler, unsuspend_user_handler,
+        update_user_role_handler,
+    },
+    handler::bookmark::{add_bookmark_handler, list_bookmarks_handler, remove_bookmark_handler},
+    handler::comment::{
+        create_comment_handler, delete_comment_handler, list_comments_handler,
+        list_moderation_queue_handler, lock_comments_handler, moderate_comment_handler,
+        update_comment_handler,
+    },
     handler::post::{
-        create_post_handler, get_post_handler, get_posts_handler, update_post_handler,
+        create_post_handler, delete_post_handler, get_my_posts_handler, get_post_by_slug_handler,
+        get_post_handler, get_posts_handler, highlight_css_handler, search_posts_handler,
+        update_post_handler,
     },
+    handler::reaction::toggle_reaction_handler,
+    handler::revision::{diff_revisions_handler, list_revisions_handler, restore_revision_handler},
+    handler::taxonomy::{create_category_handler, list_categories_handler, list_tags_handler},
     handler::user::{
-        get_me_handler, health_checker_handler, login_user_handler, logout_handler,
-        register_user_handler,
+        delete_me_handler, forgot_password_handler, get_me_handler, health_checker_handler,
+        jwks_handler, login_user_handler, logout_all_handler, logout_handler,
+        refresh_token_handler, register_user_handler, resend_verification_handler,
+        reset_password_handler, update_me_handler, update_password_handler, verify_email_handler,
     },
-    jwt_auth::auth,
+    jwt_auth::{auth, optional_auth},
     AppState,
 };
 
 pub fn create_router(app_state: Arc<AppState>) -> Router {
+    let admin_routes = Router::new()
+        .route("/users", get(list_users_handler))
+        .route(
+            "/users/:id",
+            get(get_user_handler).delete(delete_user_handler),
+        )
+        .route("/users/:id/role", patch(update_user_role_handler))
+        .route("/users/:id/suspend", post(suspend_user_handler))
+        .route("/users/:id/unsuspend", post(unsuspend_user_handler))
+        .route(
+            "/users/:id/force-password-reset",
+            post(force_password_reset_handler),
+        )
+        .route("/audit-log", get(list_audit_logs_handler))
+        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth));
+
     Router::new()
         .route("/api/healthchecker", get(health_checker_handler))
+        .route("/.well-known/jwks.json", get(jwks_handler))
         .route("/api/auth/register", post(register_user_handler))
         .route("/api/auth/login", post(login_user_handler))
+        .route("/api/auth/refresh", post(refresh_token_handler))
+        .route("/api/auth/verify-email", post(verify_email_handler))
+        .route("/api/auth/forgot-password", post(forgot_password_handler))
+        .route("/api/auth/reset-password", post(reset_password_handler))
+        .route(
+            "/api/auth/resend-verification",
+            post(resend_verification_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
         .route(
             "/api/auth/logout",
             get(logout_handler)
                 .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
         )
+        .route(
+            "/api/auth/logout-all",
+            post(logout_all_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
         .route(
             "/api/users/me",
             get(get_me_handler)
+                .patch(update_me_handler)
+                .delete(delete_me_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/users/me/password",
+            post(update_password_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/users/me/posts",
+            get(get_my_posts_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/users/me/bookmarks",
+            get(list_bookmarks_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/users/me/bookmarks/:id",
+            put(add_bookmark_handler)
+                .delete(remove_bookmark_handler)
                 .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
         )
         .route(
@@ -43,7 +115,96 @@ pub fn create_router(app_state: Arc<AppState>) -> Router {
             post(update_post_handler)
                 .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
         )
-        .route("/api/posts", get(get_posts_handler))
-        .route("/api/post/:id", get(get_post_handler))
+        .route(
+            "/api/posts",
+            get(get_posts_handler).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                optional_auth,
+            )),
+        )
+        .route(
+            "/api/posts/search",
+            get(search_posts_handler).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                optional_auth,
+            )),
+        )
+        .route("/api/posts/highlight.css", get(highlight_css_handler))
+        .route(
+            "/api/posts/by-slug/:slug",
+            get(get_post_by_slug_handler).route_layer(middleware::from_fn_with_state(
+                app_state.clone(),
+                optional_auth,
+            )),
+        )
+        .route(
+            "/api/post/:id",
+            get(get_post_handler)
+                .route_layer(middleware::from_fn_with_state(
+                    app_state.clone(),
+                    optional_auth,
+                ))
+                .merge(
+                    delete(delete_post_handler)
+                        .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+                ),
+        )
+        .route(
+            "/api/post/:id/revisions",
+            get(list_revisions_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/post/:id/revisions/diff",
+            get(diff_revisions_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/post/:id/revisions/:revision/restore",
+            post(restore_revision_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/post/:id/comments",
+            get(list_comments_handler).merge(
+                post(create_comment_handler)
+                    .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+            ),
+        )
+        .route(
+            "/api/post/:id/comments/lock",
+            post(lock_comments_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/post/:id/reactions/:reaction",
+            post(toggle_reaction_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/comments/moderation",
+            get(list_moderation_queue_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/comments/:id/moderate",
+            post(moderate_comment_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route(
+            "/api/comments/:id",
+            patch(update_comment_handler)
+                .delete(delete_comment_handler)
+                .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+        )
+        .route("/api/tags", get(list_tags_handler))
+        .route(
+            "/api/categories",
+            get(list_categories_handler).merge(
+                post(create_category_handler)
+                    .route_layer(middleware::from_fn_with_state(app_state.clone(), auth)),
+            ),
+        )
+        .nest("/api/admin", admin_routes)
         .with_state(app_state)
 }
@@ -0,0 +1,129 @@
+use deunicode::deunicode;
+
+/// Longest slug `slugify` produces, suffixes added for uniqueness come on top.
+pub const MAX_LENGTH: usize = 80;
+
+/// Used when nothing of the title survives slugification.
+pub const FALLBACK: &str = "post";
+
+/// Slugs that would clash with front-end routes.
+pub const RESERVED: &[&str] = &["admin", "api", "by-slug", "drafts", "edit", "new", "search"];
+
+/// Turns a title into a URL slug.
+///
+/// The title is transliterated to ASCII ("Ação" becomes "acao", "Größe"
+/// becomes "grosse"), lowercased, and every run of other characters becomes a
+/// single dash. Slugs longer than `MAX_LENGTH` are cut at the last word that
+/// fits, and reserved words get a "-post" suffix. The result is never empty.
+pub fn slugify(title: &str) -> String {
+    let slug = slugify_name(title);
+
+    if slug.is_empty() {
+        FALLBACK.to_string()
+    } else if RESERVED.contains(&slug.as_str()) {
+        format!("{}-{}", slug, FALLBACK)
+    } else {
+        slug
+    }
+}
+
+/// Turns a tag or category name into a slug with the same rules as `slugify`,
+/// but without the post fallback and reserved words. The result is empty when
+/// the name has no letters or digits.
+pub fn slugify_name(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in deunicode(name).chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    if slug.len() > MAX_LENGTH {
+        // cut at a word boundary, unless the first word alone is too long
+        slug.truncate(match slug[..=MAX_LENGTH].rfind('-') {
+            Some(index) if index > 0 => index,
+            _ => MAX_LENGTH,
+        });
+    }
+
+    slug.trim_end_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn transliterates_portuguese_and_german() {
+        assert_eq!(slugify("Ação rápida não é ruim"), "acao-rapida-nao-e-ruim");
+        assert_eq!(slugify("Größe über Maß"), "grosse-uber-mass");
+    }
+
+    #[test]
+    fn collapses_and_trims_separators() {
+        assert_eq!(slugify("  Hello,   World!!  "), "hello-world");
+        assert_eq!(slugify("--a -- b--"), "a-b");
+        assert_eq!(slugify("Rust 🦀 & Axum"), "rust-crab-axum");
+    }
+
+    #[test]
+    fn cuts_long_titles_at_a_word_boundary() {
+        let title = "word ".repeat(30);
+        let slug = slugify(&title);
+        assert!(slug.len() <= MAX_LENGTH);
+        assert!(slug.ends_with("word"));
+
+        let long_word = "a".repeat(MAX_LENGTH + 10);
+        assert_eq!(slugify(&long_word), "a".repeat(MAX_LENGTH));
+    }
+
+    #[test]
+    fn avoids_reserved_and_empty_slugs() {
+        assert_eq!(slugify("New"), "new-post");
+        assert_eq!(slugify("ADMIN!"), "admin-post");
+        assert_eq!(slugify("?!"), FALLBACK);
+        assert_eq!(slugify(""), FALLBACK);
+    }
+
+    #[test]
+    fn names_keep_reserved_words_and_may_be_empty() {
+        assert_eq!(slugify_name("New"), "new");
+        assert_eq!(slugify_name("Größe"), "grosse");
+        assert_eq!(slugify_name("C++"), "c");
+        assert_eq!(slugify_name("?!"), "");
+        assert_eq!(slugify_name(""), "");
+    }
+
+    proptest! {
+        #[test]
+        fn slugs_are_url_safe(title in any::<String>()) {
+            let slug = slugify(&title);
+            prop_assert!(!slug.is_empty());
+            prop_assert!(slug.len() <= MAX_LENGTH + FALLBACK.len() + 1);
+            prop_assert!(slug
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-'));
+            prop_assert!(!slug.starts_with('-') && !slug.ends_with('-'));
+            prop_assert!(!slug.contains("--"));
+            prop_assert!(!RESERVED.contains(&slug.as_str()));
+        }
+
+        #[test]
+        fn slugify_is_idempotent(title in any::<String>()) {
+            let slug = slugify(&title);
+            prop_assert_eq!(slugify(&slug), slug);
+        }
+
+        #[test]
+        fn long_titles_keep_whole_words(words in prop::collection::vec("[a-z]{1,11}[0-9]", 1..40)) {
+            let slug = slugify(&words.join(" "));
+            prop_assert!(slug.len() <= MAX_LENGTH);
+            for word in slug.split('-') {
+                prop_assert!(words.iter().any(|w| w == word));
+            }
+        }
+    }
+}
@@ -0,0 +1,228 @@
+pub mod rules;
+
+use chrono::{prelude::*, Duration};
+
+use crate::{config::Config, model::comment::CommentStatus};
+
+/// What a spam rule gets to look at: the comment and when its author last
+/// commented, so rules can be checked without touching the database.
+#[derive(Debug, Clone)]
+pub struct CommentSample<'a> {
+    pub content: &'a str,
+    /// Creation times of the author's comments within
+    /// `SpamScorer::history_window`.
+    pub recent: &'a [DateTime<Utc>],
+    pub now: DateTime<Utc>,
+}
+
+pub trait SpamRule: Send + Sync {
+    fn name(&self) -> &'static str;
+
+    /// How spammy the comment looks to this rule, 0 when nothing is wrong.
+    fn score(&self, comment: &CommentSample) -> f32;
+
+    /// How far back the rule looks at the author's earlier comments.
+    fn window(&self) -> Duration {
+        Duration::zero()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpamVerdict {
+    pub score: f32,
+    pub is_spam: bool,
+    /// Names of the rules that scored the comment.
+    pub reasons: Vec<&'static str>,
+}
+
+impl SpamVerdict {
+    /// The status a new comment (`current` is `None`) or an edited one gets.
+    /// Spam always goes to the spam folder. Trusted authors are approved
+    /// straight away, anyone else's new or edited approved comment waits for
+    /// a moderator. Edits never undo a rejection or a spam verdict.
+    pub fn comment_status(&self, trusted: bool, current: Option<CommentStatus>) -> CommentStatus {
+        if self.is_spam {
+            return CommentStatus::Spam;
+        }
+
+        match (current, trusted) {
+            (None, true) => CommentStatus::Approved,
+            (None | Some(CommentStatus::Approved), false) => CommentStatus::Pending,
+            (Some(status), _) => status,
+        }
+    }
+}
+
+/// Adds up the scores of its rules, comments reaching `threshold` are spam.
+pub struct SpamScorer {
+    rules: Vec<Box<dyn SpamRule>>,
+    threshold: f32,
+}
+
+impl SpamScorer {
+    pub fn new(threshold: f32) -> SpamScorer {
+        SpamScorer {
+            rules: Vec::new(),
+            threshold,
+        }
+    }
+
+    pub fn with_rule(mut self, rule: impl SpamRule + 'static) -> SpamScorer {
+        self.rules.push(Box::new(rule));
+        self
+    }
+
+    /// How much of the author's comment history the rules need.
+    pub fn history_window(&self) -> Duration {
+        self.rules
+            .iter()
+            .map(|rule| rule.window())
+            .max()
+            .unwrap_or_else(Duration::zero)
+    }
+
+    pub fn check(&self, comment: &CommentSample) -> SpamVerdict {
+        let mut score = 0.0;
+        let mut reasons = Vec::new();
+        for rule in &self.rules {
+            let rule_score = rule.score(comment);
+            if rule_score > 0.0 {
+                score += rule_score;
+                reasons.push(rule.name());
+            }
+        }
+
+        SpamVerdict {
+            score,
+            is_spam: score >= self.threshold,
+            reasons,
+        }
+    }
+}
+
+/// Builds the scorer from the `SPAM_*` settings.
+pub fn from_config(config: &Config) -> SpamScorer {
+    SpamScorer::new(config.spam_threshold)
+        .with_rule(rules::LinkCount::new(config.spam_max_links))
+        .with_rule(rules::Blocklist::new(config.spam_blocklist.clone()))
+        .with_rule(rules::RateLimit::new(
+            config.spam_max_comments,
+            config.spam_rate_window,
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::rules::{Blocklist, LinkCount, RateLimit};
+    use super::*;
+
+    fn sample<'a>(content: &'a str, recent: &'a [DateTime<Utc>]) -> CommentSample<'a> {
+        CommentSample {
+            content,
+            recent,
+            now: Utc.with_ymd_and_hms(2024, 3, 12, 12, 0, 0).unwrap(),
+        }
+    }
+
+    fn scorer() -> SpamScorer {
+        SpamScorer::new(1.0)
+            .with_rule(LinkCount::new(2))
+            .with_rule(Blocklist::new(vec!["cheap pills".to_string()]))
+            .with_rule(RateLimit::new(3, Duration::minutes(10)))
+    }
+
+    #[test]
+    fn lets_ordinary_comments_through() {
+        let verdict = scorer().check(&sample("Great post, see https://example.com", &[]));
+        assert_eq!(verdict.score, 0.0);
+        assert!(!verdict.is_spam);
+        assert!(verdict.reasons.is_empty());
+    }
+
+    #[test]
+    fn scores_links_over_the_limit() {
+        let rule = LinkCount::new(2);
+        let two = "http://a.example https://b.example";
+        let four = "http://a.example https://b.example [c](https://c.example) http://d.example";
+        assert_eq!(rule.score(&sample(two, &[])), 0.0);
+        assert_eq!(rule.score(&sample(four, &[])), 1.0);
+        assert!(scorer().check(&sample(four, &[])).is_spam);
+    }
+
+    #[test]
+    fn matches_blocked_phrases_ignoring_case() {
+        let verdict = scorer().check(&sample("Buy CHEAP Pills now", &[]));
+        assert!(verdict.is_spam);
+        assert_eq!(verdict.reasons, vec!["blocklist"]);
+        assert!(!scorer().check(&sample("pills are cheap", &[])).is_spam);
+    }
+
+    #[test]
+    fn flags_authors_commenting_too_fast() {
+        let now = sample("", &[]).now;
+        let recent: Vec<DateTime<Utc>> = (1..=3).map(|i| now - Duration::minutes(i)).collect();
+        let old: Vec<DateTime<Utc>> = (1..=3).map(|i| now - Duration::hours(i)).collect();
+
+        assert!(scorer().check(&sample("hi", &recent)).is_spam);
+        assert!(!scorer().check(&sample("hi", &old)).is_spam);
+        assert!(!scorer().check(&sample("hi", &recent[..2])).is_spam);
+    }
+
+    #[test]
+    fn adds_up_rules_below_the_threshold() {
+        let scorer = SpamScorer::new(1.5)
+            .with_rule(LinkCount::new(0))
+            .with_rule(Blocklist::new(vec!["casino".to_string()]));
+
+        let verdict = scorer.check(&sample("casino", &[]));
+        assert!(!verdict.is_spam);
+
+        let verdict = scorer.check(&sample("casino https://casino.example", &[]));
+        assert_eq!(verdict.score, 1.5);
+        assert!(verdict.is_spam);
+        assert_eq!(verdict.reasons, vec!["link_count", "blocklist"]);
+    }
+
+    #[test]
+    fn new_comments_wait_unless_the_author_is_trusted() {
+        let verdict = scorer().check(&sample("hi", &[]));
+        assert_eq!(verdict.comment_status(true, None), CommentStatus::Approved);
+        assert_eq!(verdict.comment_status(false, None), CommentStatus::Pending);
+
+        let spam = scorer().check(&sample("cheap pills", &[]));
+        assert_eq!(spam.comment_status(true, None), CommentStatus::Spam);
+    }
+
+    #[test]
+    fn untrusted_edits_go_back_to_moderation() {
+        let verdict = scorer().check(&sample("something else entirely", &[]));
+        let approved = Some(CommentStatus::Approved);
+        assert_eq!(
+            verdict.comment_status(false, approved),
+            CommentStatus::Pending
+        );
+        assert_eq!(
+            verdict.comment_status(true, approved),
+            CommentStatus::Approved
+        );
+
+        // edits don't get a comment out of the moderators' hands
+        for status in [
+            CommentStatus::Pending,
+            CommentStatus::Rejected,
+            CommentStatus::Spam,
+        ] {
+            assert_eq!(verdict.comment_status(true, Some(status)), status);
+            assert_eq!(verdict.comment_status(false, Some(status)), status);
+        }
+
+        let spam = scorer().check(&sample("cheap pills", &[]));
+        assert_eq!(spam.comment_status(true, approved), CommentStatus::Spam);
+    }
+
+    #[test]
+    fn history_window_covers_every_rule() {
+        assert_eq!(scorer().history_window(), Duration::minutes(10));
+        assert_eq!(SpamScorer::new(1.0).history_window(), Duration::zero());
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::Duration;
+
+use super::{CommentSample, SpamRule};
+
+/// Half a point for every link beyond `max_links`.
+pub struct LinkCount {
+    max_links: usize,
+}
+
+impl LinkCount {
+    pub fn new(max_links: usize) -> LinkCount {
+        LinkCount { max_links }
+    }
+}
+
+impl SpamRule for LinkCount {
+    fn name(&self) -> &'static str {
+        "link_count"
+    }
+
+    fn score(&self, comment: &CommentSample) -> f32 {
+        let content = comment.content.to_lowercase();
+        let links = content.matches("http://").count() + content.matches("https://").count();
+        links.saturating_sub(self.max_links) as f32 * 0.5
+    }
+}
+
+/// A point for every blocked phrase found in the comment. Phrases match
+/// anywhere in the text regardless of case.
+pub struct Blocklist {
+    phrases: Vec<String>,
+}
+
+impl Blocklist {
+    pub fn new(phrases: Vec<String>) -> Blocklist {
+        Blocklist {
+            phrases: phrases
+                .into_iter()
+                .map(|phrase| phrase.trim().to_lowercase())
+                .filter(|phrase| !phrase.is_empty())
+                .collect(),
+        }
+    }
+}
+
+impl SpamRule for Blocklist {
+    fn name(&self) -> &'static str {
+        "blocklist"
+    }
+
+    fn score(&self, comment: &CommentSample) -> f32 {
+        let content = comment.content.to_lowercase();
+        self.phrases
+            .iter()
+            .filter(|phrase| content.contains(phrase.as_str()))
+            .count() as f32
+    }
+}
+
+/// A point when the author already wrote `max_comments` comments within
+/// `window`.
+pub struct RateLimit {
+    max_comments: usize,
+    window: Duration,
+}
+
+impl RateLimit {
+    pub fn new(max_comments: usize, window: Duration) -> RateLimit {
+        RateLimit {
+            max_comments,
+            window,
+        }
+    }
+}
+
+impl SpamRule for RateLimit {
+    fn name(&self) -> &'static str {
+        "rate_limit"
+    }
+
+    fn score(&self, comment: &CommentSample) -> f32 {
+        let since = comment.now - self.window;
+        let recent = comment.recent.iter().filter(|at| **at > since).count();
+        if recent >= self.max_comments {
+            1.0
+        } else {
+            0.0
+        }
+    }
+
+    fn window(&self) -> Duration {
+        self.window
+    }
+}